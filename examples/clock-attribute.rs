@@ -22,7 +22,6 @@
 #![feature(proc_macro, unboxed_closures)]
 
 extern crate chrono;
-extern crate futures_glib;
 extern crate gtk;
 #[macro_use]
 extern crate relm;
@@ -33,7 +32,6 @@ extern crate relm_derive;
 use std::time::Duration;
 
 use chrono::{DateTime, Local};
-use futures_glib::Interval;
 use gtk::{
     Inhibit,
     LabelExt,
@@ -48,7 +46,7 @@ pub struct Model {
     time: DateTime<Local>,
 }
 
-#[derive(Msg)]
+#[derive(Clone, Msg)]
 pub enum Msg {
     Quit,
     Tick(()),
@@ -63,8 +61,7 @@ impl Widget for Win {
     }
 
     fn subscriptions(&mut self, relm: &Relm<Self>) {
-        let stream = Interval::new(Duration::from_secs(1));
-        relm.connect_exec_ignore_err(stream, Tick);
+        relm.interval(Duration::from_secs(1), Tick(()));
     }
 
     fn update(&mut self, event: Msg) {
@@ -20,7 +20,6 @@
  */
 
 extern crate chrono;
-extern crate futures_glib;
 extern crate gtk;
 #[macro_use]
 extern crate relm;
@@ -30,7 +29,6 @@ extern crate relm_derive;
 use std::time::Duration;
 
 use chrono::Local;
-use futures_glib::Interval;
 use gtk::{
     ContainerExt,
     Inhibit,
@@ -44,7 +42,7 @@ use relm::{Relm, Update, Widget};
 
 use self::Msg::*;
 
-#[derive(Msg)]
+#[derive(Clone, Msg)]
 enum Msg {
     Quit,
     Tick(()),
@@ -65,8 +63,7 @@ impl Update for Win {
     }
 
     fn subscriptions(&mut self, relm: &Relm<Self>) {
-        let stream = Interval::new(Duration::from_secs(1));
-        relm.connect_exec_ignore_err(stream, Tick);
+        relm.interval(Duration::from_secs(1), Tick(()));
     }
 
     fn update(&mut self, event: Msg) {
@@ -0,0 +1,113 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+extern crate gtk;
+#[macro_use]
+extern crate relm;
+#[macro_use]
+extern crate relm_derive;
+
+use std::thread;
+use std::time::Duration;
+
+use gtk::{
+    ContainerExt,
+    Inhibit,
+    Label,
+    LabelExt,
+    WidgetExt,
+    Window,
+    WindowType,
+};
+use relm::{Relm, Update, Widget};
+
+use self::Msg::*;
+
+#[derive(Msg)]
+enum Msg {
+    Quit,
+    Value(u64),
+}
+
+struct Win {
+    label: Label,
+    window: Window,
+}
+
+impl Update for Win {
+    type Model = ();
+    type ModelParam = ();
+    type Msg = Msg;
+
+    fn model(_: &Relm<Self>, _: ()) -> () {
+        ()
+    }
+
+    fn subscriptions(&mut self, relm: &Relm<Self>) {
+        let sender = relm.sender();
+        thread::spawn(move || {
+            let mut sum = 0;
+            for i in 0.. {
+                sum += i;
+                thread::sleep(Duration::from_millis(200));
+                if sender.send(Value(sum)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Quit => gtk::main_quit(),
+            Value(value) => self.label.set_text(&value.to_string()),
+        }
+    }
+}
+
+impl Widget for Win {
+    type Root = Window;
+
+    fn root(&self) -> Self::Root {
+        self.window.clone()
+    }
+
+    fn view(relm: &Relm<Self>, _model: Self::Model) -> Self {
+        let label = Label::new(None);
+
+        let window = Window::new(WindowType::Toplevel);
+
+        window.add(&label);
+
+        window.show_all();
+
+        connect!(relm, window, connect_delete_event(_, _), return (Some(Quit), Inhibit(false)));
+
+        Win {
+            label: label,
+            window: window,
+        }
+    }
+}
+
+fn main() {
+    Win::run(()).unwrap();
+}
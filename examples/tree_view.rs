@@ -27,16 +27,27 @@ struct Directory {
 
 #[derive(Msg)]
 enum Msg {
+    Entries(Vec<(String, bool)>),
     ItemSelect,
     Quit,
+    ReadError(io::Error),
 }
 
 struct Win {
     tree_view: TreeView,
     model: Directory,
+    relm: Relm<Win>,
     window: Window,
 }
 
+impl Win {
+    // Reading a directory can be slow, so it is done on the worker pool.
+    fn load_current_dir(&self) {
+        let dir = self.model.current_dir.clone();
+        self.relm.spawn_blocking(move || read_entries(&dir), Msg::Entries, Msg::ReadError);
+    }
+}
+
 impl Update for Win {
     type Model = Directory;
     type ModelParam = ();
@@ -51,6 +62,10 @@ impl Update for Win {
 
     fn update(&mut self, event: Msg) {
         match event {
+            Msg::Entries(entries) => {
+                let new_model = create_model(&entries);
+                self.tree_view.set_model(Some(&new_model));
+            },
             Msg::ItemSelect => {
                 let selection = self.tree_view.get_selection();
                 if let Some((list_model, iter)) = selection.get_selected() {
@@ -76,13 +91,12 @@ impl Update for Win {
                             self.model.current_dir.join(dir_name)
                         };
                         self.model.current_dir = new_dir;
-                        let new_model = create_and_fill_model(&self.model.current_dir).unwrap();
-
-                        self.tree_view.set_model(Some(&new_model));
+                        self.load_current_dir();
                     }
                 }
             },
             Msg::Quit => gtk::main_quit(),
+            Msg::ReadError(error) => println!("Cannot read directory: {}", error),
         }
     }
 }
@@ -111,9 +125,6 @@ impl Widget for Win {
         column.add_attribute(&cell, "text", 0);
         tree_view.append_column(&column);
 
-        let store_model = create_and_fill_model(&model.current_dir).unwrap();
-        tree_view.set_model(Some(&store_model));
-
         vbox.add(&tree_view);
         window.add(&vbox);
 
@@ -122,22 +133,20 @@ impl Widget for Win {
         connect!(relm, tree_view, connect_cursor_changed(_), Msg::ItemSelect);
         connect!(relm, window, connect_delete_event(_, _), return (Some(Msg::Quit), Inhibit(false)));
 
-        Win {
+        let win = Win {
             tree_view,
             model,
+            relm: relm.clone(),
             window,
-        }
+        };
+        win.load_current_dir();
+        win
     }
 }
 
-fn create_and_fill_model(dir_str: &PathBuf) -> io::Result<gtk::ListStore> {
-    // Single row model
-    let model = gtk::ListStore::new(&[String::static_type(), bool::static_type()]);
-
+fn read_entries(dir_str: &PathBuf) -> io::Result<Vec<(String, bool)>> {
     // Add the parent directory
-    model.insert_with_values(None,
-                            &[VALUE_COL as u32, IS_DIR_COL as u32],
-                            &[&"..", &true]);
+    let mut entries = vec![("..".to_string(), true)];
 
     let entry_iter = fs::read_dir(dir_str)?.filter_map(|x| x.ok());
     for entry in entry_iter {
@@ -149,13 +158,23 @@ fn create_and_fill_model(dir_str: &PathBuf) -> io::Result<gtk::ListStore> {
                 } else {
                     (file_name, false)
                 };
-                model.insert_with_values(None,
-                                        &[VALUE_COL as u32, IS_DIR_COL as u32],
-                                        &[&final_name, &is_dir]);
+                entries.push((final_name, is_dir));
             }
         }
     }
-    Ok(model)
+    Ok(entries)
+}
+
+fn create_model(entries: &[(String, bool)]) -> gtk::ListStore {
+    // Single row model
+    let model = gtk::ListStore::new(&[String::static_type(), bool::static_type()]);
+
+    for &(ref name, is_dir) in entries {
+        model.insert_with_values(None,
+                                &[VALUE_COL as u32, IS_DIR_COL as u32],
+                                &[name, &is_dir]);
+    }
+    model
 }
 
 
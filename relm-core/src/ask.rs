@@ -0,0 +1,66 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use futures::{Future, Poll};
+use futures::sync::oneshot::{self, Canceled, Receiver};
+
+/// A slot to answer a request made with [`EventStream::ask()`](struct.EventStream.html#method.ask).
+///
+/// If it is dropped without answering, the `Response` resolves to an error.
+pub struct Reply<T> {
+    sender: oneshot::Sender<T>,
+}
+
+impl<T> Reply<T> {
+    /// Answer the request with `value`.
+    pub fn reply(self, value: T) {
+        // NOTE: an error only means the requester is not waiting for the response anymore.
+        let _ = self.sender.send(value);
+    }
+}
+
+/// The response to a request made with [`EventStream::ask()`](struct.EventStream.html#method.ask).
+/// This is a `Future` resolving to the answer, which can be connected to a component with
+/// `Relm::connect_exec()`.
+#[must_use]
+pub struct Response<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> Future for Response<T> {
+    type Item = T;
+    type Error = Canceled;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.receiver.poll()
+    }
+}
+
+pub fn channel<T>() -> (Reply<T>, Response<T>) {
+    let (sender, receiver) = oneshot::channel();
+    let reply = Reply {
+        sender,
+    };
+    let response = Response {
+        receiver,
+    };
+    (reply, response)
+}
@@ -35,14 +35,22 @@
 
 extern crate futures;
 
+mod ask;
+
+use std::any::Any;
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::io::Error;
-use std::rc::Rc;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use futures::{Async, Poll, Stream};
 use futures::task::{self, Task};
 
+pub use ask::{Reply, Response};
+
 /// A lock is used to temporarily stop emitting messages.
 #[must_use]
 pub struct Lock<MSG> {
@@ -51,18 +59,230 @@ pub struct Lock<MSG> {
 
 impl<MSG> Drop for Lock<MSG> {
     fn drop(&mut self) {
-        self.stream.borrow_mut().locked = false;
+        self.stream.borrow_mut().locked -= 1;
     }
 }
 
+/// A pause is used to temporarily hold the emitted messages.
+/// What happens to these messages depends on the `PausePolicy` of the stream.
+#[must_use]
+pub struct Pause<MSG> {
+    stream: Rc<RefCell<_EventStream<MSG>>>,
+}
+
+impl<MSG> Drop for Pause<MSG> {
+    fn drop(&mut self) {
+        let events = {
+            let mut stream = self.stream.borrow_mut();
+            stream.paused -= 1;
+            if stream.paused > 0 {
+                return;
+            }
+            mem::take(&mut stream.paused_events)
+        };
+        let stream = EventStream {
+            stream: self.stream.clone(),
+        };
+        for (pending, priority) in events {
+            stream.emit_pending(pending, priority);
+        }
+    }
+}
+
+/// Priority of a message.
+/// The messages with a higher priority are received before the others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Priority {
+    /// For messages that should be handled as soon as possible, like cancellation.
+    High,
+    /// The default priority.
+    Normal,
+    /// For messages that can wait until there are no other messages, like progress updates.
+    Idle,
+}
+
+/// Policy for the messages emitted while a bounded stream is full.
+///
+/// The observers are called in any case: this policy only affects the pending messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the new message.
+    DropNewest,
+    /// Discard the oldest pending message of the lowest priority.
+    DropOldest,
+    /// Replace the pending message of the same enum variant and priority with the new message.
+    /// If there's no such message, discard the oldest pending message, like `DropOldest`.
+    Coalesce,
+}
+
+/// Policy for the messages emitted while a stream is paused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PausePolicy {
+    /// Discard the messages, like a `Lock`.
+    Discard,
+    /// Queue the messages and emit them in order when the stream is resumed.
+    Buffer,
+    /// Like `Buffer`, but only keep the last message of each enum variant.
+    Coalesce,
+}
+
+/// A handle to an observer added to an `EventStream`.
+/// It can be used to remove the observer from the stream.
+pub struct ObserverHandle<MSG> {
+    id: usize,
+    stream: Weak<RefCell<_EventStream<MSG>>>,
+}
+
+impl<MSG> ObserverHandle<MSG> {
+    /// Remove the observer from the stream.
+    pub fn disconnect(&self) {
+        if let Some(stream) = self.stream.upgrade() {
+            stream.borrow_mut().observers.retain(|observer| observer.id != self.id);
+        }
+    }
+
+    /// Get a guard which removes the observer from the stream when it goes out of scope.
+    pub fn guard(self) -> ObserverGuard<MSG> {
+        ObserverGuard {
+            handle: self,
+        }
+    }
+}
+
+/// A guard removing an observer from its stream when it goes out of scope.
+#[must_use]
+pub struct ObserverGuard<MSG> {
+    handle: ObserverHandle<MSG>,
+}
+
+impl<MSG> Drop for ObserverGuard<MSG> {
+    fn drop(&mut self) {
+        self.handle.disconnect();
+    }
+}
+
+/// A key used to coalesce messages.
+trait Key {
+    fn as_any(&self) -> &Any;
+    fn equals(&self, other: &Key) -> bool;
+}
+
+impl<T: PartialEq + 'static> Key for T {
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn equals(&self, other: &Key) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+}
+
+/// A message that was not received yet.
+struct Pending<MSG> {
+    emitted: Instant,
+    event: MSG,
+    key: Option<Box<Key>>,
+}
+
+impl<MSG> Pending<MSG> {
+    fn has_key(&self, key: &Option<Box<Key>>) -> bool {
+        match (&self.key, key) {
+            (Some(key), Some(other)) => key.equals(&**other),
+            _ => false,
+        }
+    }
+}
+
+struct Observer<MSG> {
+    callback: Rc<Fn(&MSG)>,
+    id: usize,
+}
+
 struct _EventStream<MSG> {
+    capacity: Option<usize>,
+    close_callbacks: Vec<Box<Fn()>>,
+    // Whether the stream is closed once the pending messages are received.
+    closing: bool,
+    dropped: usize,
+    // One queue for each priority.
+    events: [VecDeque<Pending<MSG>>; 3],
+    locked: usize,
+    next_observer_id: usize,
+    observers: Vec<Observer<MSG>>,
+    overflow_policy: OverflowPolicy,
+    pause_policy: PausePolicy,
+    paused: usize,
+    paused_events: VecDeque<(Pending<MSG>, Priority)>,
+    sender: Option<Arc<Mutex<_Sender<MSG>>>>,
+    // The streams this stream is derived from, kept alive as long as this stream.
+    sources: Vec<Rc<Any>>,
+    task: Option<Task>,
+    terminated: bool,
+    wait_time: Option<Duration>,
+}
+
+impl<MSG> _EventStream<MSG> {
+    fn drop_oldest(&mut self) {
+        if let Some(events) = self.events.iter_mut().rev().find(|events| !events.is_empty()) {
+            let _ = events.pop_front();
+        }
+    }
+
+    fn queued_count(&self) -> usize {
+        self.events.iter().map(VecDeque::len).sum()
+    }
+}
+
+impl<MSG> Drop for _EventStream<MSG> {
+    fn drop(&mut self) {
+        // Nothing will receive the messages sent from other threads anymore.
+        if let Some(ref sender) = self.sender {
+            if let Ok(mut sender) = sender.lock() {
+                sender.terminated = true;
+            }
+        }
+    }
+}
+
+struct _Sender<MSG> {
     events: VecDeque<MSG>,
-    locked: bool,
-    observers: Vec<Rc<Fn(&MSG)>>,
     task: Option<Task>,
     terminated: bool,
 }
 
+/// A handle to send messages to an `EventStream` from any thread.
+///
+/// The messages are queued and delivered to the stream (and its observers) from the thread
+/// polling the stream, i.e. the thread running the main context.
+pub struct Sender<MSG> {
+    sender: Arc<Mutex<_Sender<MSG>>>,
+}
+
+impl<MSG> Clone for Sender<MSG> {
+    fn clone(&self) -> Self {
+        Sender {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<MSG> Sender<MSG> {
+    /// Send the `event` message to the stream.
+    /// Give the message back if the stream is closed.
+    pub fn send(&self, event: MSG) -> Result<(), MSG> {
+        let mut sender = self.sender.lock().expect("sender lock");
+        if sender.terminated {
+            return Err(event);
+        }
+        sender.events.push_back(event);
+        // Notifying the task wakes up the main context the stream is executed on.
+        if let Some(ref task) = sender.task {
+            task.notify();
+        }
+        Ok(())
+    }
+}
+
 /// A stream of messages to be used for widget/signal communication and inter-widget communication.
 pub struct EventStream<MSG> {
     stream: Rc<RefCell<_EventStream<MSG>>>,
@@ -76,69 +296,464 @@ impl<MSG> Clone for EventStream<MSG> {
     }
 }
 
+impl<MSG> Default for EventStream<MSG> {
+    fn default() -> Self {
+        EventStream::new()
+    }
+}
+
+/// A weak reference to an `EventStream`.
+pub struct WeakEventStream<MSG> {
+    stream: Weak<RefCell<_EventStream<MSG>>>,
+}
+
+impl<MSG> Clone for WeakEventStream<MSG> {
+    fn clone(&self) -> Self {
+        WeakEventStream {
+            stream: self.stream.clone(),
+        }
+    }
+}
+
+impl<MSG> WeakEventStream<MSG> {
+    /// Get the stream back if it is still alive.
+    pub fn upgrade(&self) -> Option<EventStream<MSG>> {
+        self.stream.upgrade()
+            .map(|stream| EventStream {
+                stream,
+            })
+    }
+}
+
 impl<MSG> EventStream<MSG> {
     /// Create a new event stream.
     pub fn new() -> Self {
         EventStream {
             stream: Rc::new(RefCell::new(_EventStream {
-                events: VecDeque::new(),
-                locked: false,
+                capacity: None,
+                close_callbacks: vec![],
+                closing: false,
+                dropped: 0,
+                events: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+                locked: 0,
+                next_observer_id: 0,
                 observers: vec![],
+                overflow_policy: OverflowPolicy::DropNewest,
+                pause_policy: PausePolicy::Buffer,
+                paused: 0,
+                paused_events: VecDeque::new(),
+                sender: None,
+                sources: vec![],
                 task: None,
                 terminated: false,
+                wait_time: None,
             })),
         }
     }
 
+    /// Create a new event stream holding at most `capacity` pending messages.
+    /// What happens to the messages emitted when the stream is full depends on `policy`.
+    pub fn bounded(capacity: usize, policy: OverflowPolicy) -> Self {
+        let stream = Self::new();
+        stream.set_capacity(capacity, policy);
+        stream
+    }
+
+    /// Limit the number of pending messages of the stream to `capacity`.
+    /// What happens to the messages emitted when the stream is full depends on `policy`.
+    pub fn set_capacity(&self, capacity: usize, policy: OverflowPolicy) {
+        assert!(capacity > 0, "the capacity of an EventStream must be greater than 0");
+        let mut stream = self.stream.borrow_mut();
+        stream.capacity = Some(capacity);
+        stream.overflow_policy = policy;
+    }
+
+    /// Get the number of messages that were discarded because the stream was full.
+    pub fn dropped_count(&self) -> usize {
+        self.stream.borrow().dropped
+    }
+
+    /// Get the time the last received message waited in the stream before being received.
+    pub fn wait_time(&self) -> Option<Duration> {
+        self.stream.borrow().wait_time
+    }
+
+    /// Get the number of pending messages, i.e. the messages that were not received yet.
+    pub fn queued_count(&self) -> usize {
+        self.stream.borrow().queued_count()
+    }
+
     /// Close the event stream, i.e. stop processing messages.
     pub fn close(&self) -> Result<(), Error> {
-        let mut stream = self.stream.borrow_mut();
-        stream.terminated = true;
-        // TODO: document why it is needed.
-        if let Some(ref task) = stream.task {
-            task.notify();
+        let close_callbacks = {
+            let mut stream = self.stream.borrow_mut();
+            stream.terminated = true;
+            if let Some(ref sender) = stream.sender {
+                let mut sender = sender.lock().expect("sender lock");
+                sender.terminated = true;
+                sender.events.clear();
+            }
+            // TODO: document why it is needed.
+            if let Some(ref task) = stream.task {
+                task.notify();
+            }
+            mem::take(&mut stream.close_callbacks)
+        };
+        // NOTE: the stream is not borrowed while calling the callbacks since they might use it.
+        for callback in close_callbacks {
+            callback();
+        }
+        Ok(())
+    }
+
+    /// Close the event stream once the pending messages are received, e.g. to process a `Quit`
+    /// message emitted right before the stream needs to be closed.
+    /// The stream is closed right away if there is no pending message.
+    pub fn close_after_pending(&self) -> Result<(), Error> {
+        if self.queued_count() == 0 {
+            return self.close();
         }
+        self.stream.borrow_mut().closing = true;
         Ok(())
     }
 
+    /// Add a callback called when this stream is closed.
+    /// If the stream is already closed, the callback is called right away.
+    pub fn on_close<CALLBACK: Fn() + 'static>(&self, callback: CALLBACK) {
+        if self.is_closed() {
+            callback();
+        }
+        else {
+            self.stream.borrow_mut().close_callbacks.push(Box::new(callback));
+        }
+    }
+
     /// Send the `event` message to the stream and the observers.
     pub fn emit(&self, event: MSG) {
-        if !self.stream.borrow().locked {
+        self.emit_with_priority(event, Priority::Normal);
+    }
+
+    /// Send the `event` message to the stream and the observers.
+    /// The observers are called right away, but the stream will receive this message before
+    /// (or after) the pending messages with a lower (or higher) `priority`.
+    pub fn emit_with_priority(&self, event: MSG, priority: Priority) {
+        self.emit_pending(Pending {
+            emitted: Instant::now(),
+            event,
+            key: None,
+        }, priority);
+    }
+
+    /// Send the `event` message to the stream and the observers.
+    /// If a message emitted with the same `key` was not received yet, it is replaced by `event`.
+    pub fn emit_coalesced<KEY: PartialEq + 'static>(&self, event: MSG, key: KEY) {
+        self.emit_pending(Pending {
+            emitted: Instant::now(),
+            event,
+            key: Some(Box::new(key)),
+        }, Priority::Normal);
+    }
+
+    /// Send the `event` message to the stream and the observers.
+    /// If a message of the same enum variant, emitted with this method, was not received yet, it
+    /// is replaced by `event`.
+    pub fn emit_coalesced_variant(&self, event: MSG)
+        where MSG: 'static,
+    {
+        let variant = mem::discriminant(&event);
+        self.emit_coalesced(event, variant);
+    }
+
+    /// Send the `event` message to the observers only: the stream does not receive it.
+    pub fn notify_observers(&self, event: &MSG) {
+        // NOTE: the observers are collected first because they can add or remove observers.
+        let observers: Vec<_> = self.stream.borrow().observers.iter()
+            .map(|observer| observer.callback.clone())
+            .collect();
+        for observer in observers {
+            observer(event);
+        }
+    }
+
+    fn emit_pending(&self, pending: Pending<MSG>, priority: Priority) {
+        if self.stream.borrow().paused > 0 {
+            self.hold(pending, priority);
+        }
+        else if self.stream.borrow().locked == 0 {
             if let Some(ref task) = self.stream.borrow().task {
                 task.notify();
             }
 
-            let len = self.stream.borrow().observers.len();
-            for i in 0..len {
-                let observer = self.stream.borrow().observers[i].clone();
-                observer(&event);
-            }
+            self.notify_observers(&pending.event);
+            self.push_event(pending, priority);
+        }
+    }
 
-            self.stream.borrow_mut().events.push_back(event);
+    fn push_event(&self, pending: Pending<MSG>, priority: Priority) {
+        let mut stream = self.stream.borrow_mut();
+        let events = &mut stream.events[priority as usize];
+        if let Some(index) = events.iter().position(|event| event.has_key(&pending.key)) {
+            events[index] = pending;
+            return;
         }
+        let full = stream.capacity.is_some_and(|capacity| stream.queued_count() >= capacity);
+        if full {
+            stream.dropped += 1;
+            match stream.overflow_policy {
+                OverflowPolicy::DropNewest => return,
+                OverflowPolicy::DropOldest => stream.drop_oldest(),
+                OverflowPolicy::Coalesce => {
+                    let variant = mem::discriminant(&pending.event);
+                    let index = stream.events[priority as usize].iter()
+                        .position(|event| mem::discriminant(&event.event) == variant);
+                    if let Some(index) = index {
+                        stream.events[priority as usize][index] = pending;
+                        return;
+                    }
+                    stream.drop_oldest();
+                },
+            }
+        }
+        stream.events[priority as usize].push_back(pending);
     }
 
     fn get_event(&self) -> Option<MSG> {
-        self.stream.borrow_mut().events.pop_front()
+        let mut stream = self.stream.borrow_mut();
+        let pending = stream.events.iter_mut()
+            .filter_map(|events| events.pop_front())
+            .next();
+        pending.map(|pending| {
+            stream.wait_time = Some(pending.emitted.elapsed());
+            pending.event
+        })
+    }
+
+    fn hold(&self, pending: Pending<MSG>, priority: Priority) {
+        let mut stream = self.stream.borrow_mut();
+        if stream.locked > 0 {
+            return;
+        }
+        let index =
+            match stream.pause_policy {
+                PausePolicy::Discard => return,
+                PausePolicy::Buffer => stream.paused_events.iter()
+                    .position(|(event, _)| event.has_key(&pending.key)),
+                PausePolicy::Coalesce => {
+                    let variant = mem::discriminant(&pending.event);
+                    stream.paused_events.iter()
+                        .position(|(event, _)| mem::discriminant(&event.event) == variant)
+                },
+            };
+        match index {
+            Some(index) => stream.paused_events[index] = (pending, priority),
+            None => stream.paused_events.push_back((pending, priority)),
+        }
+    }
+
+    /// Send a request to the stream and get a `Future` resolving to its answer.
+    /// The message is created by `callback` with the `Reply` which the receiver uses to answer.
+    ///
+    /// ```ignore
+    /// // In the update() method of the receiver:
+    /// HasUnsavedChanges(reply) => reply.reply(self.model.modified),
+    ///
+    /// // In the requester:
+    /// let response = child.stream().ask(HasUnsavedChanges);
+    /// relm.connect_exec_ignore_err(response, UnsavedChanges);
+    /// ```
+    pub fn ask<CALLBACK, T>(&self, callback: CALLBACK) -> Response<T>
+        where CALLBACK: FnOnce(Reply<T>) -> MSG,
+    {
+        let (reply, response) = ask::channel();
+        self.emit(callback(reply));
+        response
     }
 
     /// Lock the stream (don't emit message) until the `Lock` goes out of scope.
+    /// Locks can be nested: the stream is unlocked when all of them are dropped.
     pub fn lock(&self) -> Lock<MSG> {
-        self.stream.borrow_mut().locked = true;
+        self.stream.borrow_mut().locked += 1;
         Lock {
             stream: self.stream.clone(),
         }
     }
 
-    fn is_terminated(&self) -> bool {
+    /// Pause the stream until the `Pause` goes out of scope.
+    /// The messages emitted in the meantime are handled according to the `PausePolicy` of the
+    /// stream (by default, they are buffered and emitted when the stream is resumed).
+    /// Pauses can be nested: the stream is resumed when all of them are dropped.
+    pub fn pause(&self) -> Pause<MSG> {
+        self.stream.borrow_mut().paused += 1;
+        Pause {
+            stream: self.stream.clone(),
+        }
+    }
+
+    /// Set the policy for the messages emitted while the stream is paused.
+    pub fn set_pause_policy(&self, policy: PausePolicy) {
+        self.stream.borrow_mut().pause_policy = policy;
+    }
+
+    /// Check whether the stream was closed.
+    pub fn is_closed(&self) -> bool {
         let stream = self.stream.borrow();
         stream.terminated
     }
 
+    /// Get a `Sender` to emit messages to this stream from another thread.
+    pub fn sender(&self) -> Sender<MSG> {
+        let mut stream = self.stream.borrow_mut();
+        let terminated = stream.terminated;
+        // NOTE: the stream might already be waiting for messages, so the sender must be able to
+        // wake it up right away.
+        let task = stream.task.clone();
+        let sender = stream.sender.get_or_insert_with(|| Arc::new(Mutex::new(_Sender {
+            events: VecDeque::new(),
+            task,
+            terminated,
+        })));
+        Sender {
+            sender: sender.clone(),
+        }
+    }
+
+    /// Emit the messages sent from other threads.
+    fn receive_sent_events(&self) {
+        let events = {
+            let stream = self.stream.borrow();
+            match stream.sender {
+                Some(ref sender) => {
+                    let mut sender = sender.lock().expect("sender lock");
+                    // Register the task before taking the events so that a message sent right
+                    // after will wake up the stream.
+                    sender.task = Some(task::current());
+                    mem::take(&mut sender.events)
+                },
+                None => return,
+            }
+        };
+        for event in events {
+            self.emit(event);
+        }
+    }
+
     /// Add an observer to the event stream.
     /// This callback will be called every time a message is emmited.
-    pub fn observe<CALLBACK: Fn(&MSG) + 'static>(&self, callback: CALLBACK) {
-        self.stream.borrow_mut().observers.push(Rc::new(callback));
+    ///
+    /// The returned handle can be used to remove the observer.
+    pub fn observe<CALLBACK: Fn(&MSG) + 'static>(&self, callback: CALLBACK) -> ObserverHandle<MSG> {
+        let id = self.next_observer_id();
+        self.add_observer(id, Rc::new(callback))
+    }
+
+    /// Add an observer which sends messages to `stream` without keeping it alive.
+    /// The observer is removed as soon as `stream` is dropped or closed.
+    pub fn observe_weak<CALLBACK, DSTMSG>(&self, stream: &EventStream<DSTMSG>, callback: CALLBACK)
+            -> ObserverHandle<MSG>
+        where CALLBACK: Fn(&EventStream<DSTMSG>, &MSG) + 'static,
+              DSTMSG: 'static,
+              MSG: 'static,
+    {
+        let id = self.next_observer_id();
+        let dst_stream = stream.downgrade();
+        let src_stream = Rc::downgrade(&self.stream);
+        self.add_observer(id, Rc::new(move |msg| {
+            match dst_stream.upgrade() {
+                Some(ref stream) if !stream.is_closed() => callback(stream, msg),
+                _ => {
+                    if let Some(src_stream) = src_stream.upgrade() {
+                        src_stream.borrow_mut().observers.retain(|observer| observer.id != id);
+                    }
+                },
+            }
+        }))
+    }
+
+    fn add_observer(&self, id: usize, callback: Rc<Fn(&MSG)>) -> ObserverHandle<MSG> {
+        self.stream.borrow_mut().observers.push(Observer {
+            callback,
+            id,
+        });
+        ObserverHandle {
+            id,
+            stream: Rc::downgrade(&self.stream),
+        }
+    }
+
+    fn next_observer_id(&self) -> usize {
+        let mut stream = self.stream.borrow_mut();
+        let id = stream.next_observer_id;
+        stream.next_observer_id += 1;
+        id
+    }
+
+    /// Create a new stream receiving the messages of this stream converted by `callback`.
+    ///
+    /// ## Note
+    /// Like the other combinators, this method takes the stream by value so that it is called
+    /// instead of `Stream::map()`: clone the stream to keep using it, e.g.
+    /// `relm.stream().clone().map(…)`.
+    pub fn map<CALLBACK, NEWMSG>(self, callback: CALLBACK) -> EventStream<NEWMSG>
+        where CALLBACK: Fn(&MSG) -> NEWMSG + 'static,
+              MSG: 'static,
+              NEWMSG: 'static,
+    {
+        self.filter_map(move |msg| Some(callback(msg)))
+    }
+
+    /// Create a new stream receiving the messages of this stream for which `callback` returns
+    /// `true`.
+    pub fn filter<CALLBACK>(self, callback: CALLBACK) -> EventStream<MSG>
+        where CALLBACK: Fn(&MSG) -> bool + 'static,
+              MSG: Clone + 'static,
+    {
+        self.filter_map(move |msg| {
+            if callback(msg) {
+                Some(msg.clone())
+            }
+            else {
+                None
+            }
+        })
+    }
+
+    /// Create a new stream receiving the messages of this stream converted by `callback`,
+    /// ignoring the messages for which it returns `None`.
+    ///
+    /// The new stream keeps this stream alive, but not the other way around: this stream stops
+    /// sending messages to the new stream when it is dropped or closed.
+    pub fn filter_map<CALLBACK, NEWMSG>(self, callback: CALLBACK) -> EventStream<NEWMSG>
+        where CALLBACK: Fn(&MSG) -> Option<NEWMSG> + 'static,
+              MSG: 'static,
+              NEWMSG: 'static,
+    {
+        let stream = EventStream::new();
+        let _ = self.observe_weak(&stream, move |stream, msg| {
+            if let Some(msg) = callback(msg) {
+                stream.emit(msg);
+            }
+        });
+        stream.stream.borrow_mut().sources.push(self.stream);
+        stream
+    }
+
+    /// Create a new stream receiving the messages of both this stream and `other`.
+    pub fn merge(self, other: &EventStream<MSG>) -> EventStream<MSG>
+        where MSG: Clone + 'static,
+    {
+        let stream = EventStream::new();
+        for source in [self, other.clone()] {
+            let _ = source.observe_weak(&stream, |stream, msg| stream.emit(msg.clone()));
+            stream.stream.borrow_mut().sources.push(source.stream);
+        }
+        stream
+    }
+
+    /// Get a weak reference to this stream, which does not keep it alive.
+    pub fn downgrade(&self) -> WeakEventStream<MSG> {
+        WeakEventStream {
+            stream: Rc::downgrade(&self.stream),
+        }
     }
 }
 
@@ -147,10 +762,11 @@ impl<MSG: 'static> Stream for EventStream<MSG> {
     type Error = ();
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        if self.is_terminated() {
+        if self.is_closed() {
             Ok(Async::Ready(None))
         }
         else {
+            self.receive_sent_events();
             match self.get_event() {
                 Some(event) => {
                     let mut stream = self.stream.borrow_mut();
@@ -158,6 +774,11 @@ impl<MSG: 'static> Stream for EventStream<MSG> {
                     Ok(Async::Ready(Some(event)))
                 },
                 None => {
+                    let closing = self.stream.borrow().closing;
+                    if closing {
+                        let _ = self.close();
+                        return Ok(Async::Ready(None));
+                    }
                     let mut stream = self.stream.borrow_mut();
                     stream.task = Some(task::current());
                     Ok(Async::NotReady)
@@ -166,3 +787,323 @@ impl<MSG: 'static> Stream for EventStream<MSG> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    use futures::{Async, Future, Stream};
+    use futures::executor::{self, Notify};
+
+    use super::{EventStream, ObserverHandle, OverflowPolicy, PausePolicy, Priority, Reply};
+
+    use self::Msg::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Msg {
+        Change(i32),
+        Click,
+    }
+
+    fn collect(stream: &EventStream<Msg>, count: u64) -> Vec<Msg> {
+        stream.clone().take(count).wait()
+            .map(|msg| msg.expect("message"))
+            .collect()
+    }
+
+    #[test]
+    fn nested_locks() {
+        let stream = EventStream::new();
+        {
+            let _lock = stream.lock();
+            {
+                let _lock = stream.lock();
+            }
+            stream.emit(Click);
+        }
+        stream.emit(Change(1));
+        assert_eq!(collect(&stream, 1), vec![Change(1)]);
+    }
+
+    #[test]
+    fn pause_buffer() {
+        let stream = EventStream::new();
+        {
+            let _pause = stream.pause();
+            stream.emit(Change(1));
+            {
+                let _pause = stream.pause();
+                stream.emit(Click);
+            }
+            stream.emit(Change(2));
+        }
+        assert_eq!(collect(&stream, 3), vec![Change(1), Click, Change(2)]);
+    }
+
+    #[test]
+    fn pause_coalesce() {
+        let stream = EventStream::new();
+        stream.set_pause_policy(PausePolicy::Coalesce);
+        {
+            let _pause = stream.pause();
+            stream.emit(Change(1));
+            stream.emit(Click);
+            stream.emit(Change(2));
+        }
+        stream.set_pause_policy(PausePolicy::Discard);
+        {
+            let _pause = stream.pause();
+            stream.emit(Change(3));
+        }
+        stream.emit(Click);
+        assert_eq!(collect(&stream, 3), vec![Change(2), Click, Click]);
+    }
+
+    #[test]
+    fn priority() {
+        let stream = EventStream::new();
+        stream.emit_with_priority(Change(1), Priority::Idle);
+        stream.emit(Change(2));
+        stream.emit_with_priority(Click, Priority::High);
+        stream.emit(Change(3));
+        assert_eq!(collect(&stream, 4), vec![Click, Change(2), Change(3), Change(1)]);
+    }
+
+    #[test]
+    fn bounded() {
+        let stream = EventStream::bounded(2, OverflowPolicy::DropNewest);
+        stream.emit(Change(1));
+        stream.emit(Change(2));
+        stream.emit(Click);
+        assert_eq!(stream.queued_count(), 2);
+        assert_eq!(stream.dropped_count(), 1);
+        assert_eq!(collect(&stream, 2), vec![Change(1), Change(2)]);
+
+        stream.set_capacity(2, OverflowPolicy::DropOldest);
+        stream.emit(Change(1));
+        stream.emit_with_priority(Click, Priority::Idle);
+        stream.emit(Change(2));
+        assert_eq!(collect(&stream, 2), vec![Change(1), Change(2)]);
+
+        stream.set_capacity(2, OverflowPolicy::Coalesce);
+        stream.emit(Change(1));
+        stream.emit(Click);
+        stream.emit(Change(2));
+        assert_eq!(stream.dropped_count(), 3);
+        assert_eq!(collect(&stream, 2), vec![Change(2), Click]);
+    }
+
+    #[test]
+    fn coalesce() {
+        let stream = EventStream::new();
+        stream.emit_coalesced(Change(1), "change");
+        stream.emit(Click);
+        stream.emit_coalesced(Change(2), "change");
+        stream.emit_coalesced(Change(3), "other change");
+        stream.emit_coalesced_variant(Change(4));
+        stream.emit_coalesced_variant(Change(5));
+        stream.emit(Change(6));
+        assert_eq!(collect(&stream, 5), vec![Change(2), Click, Change(3), Change(5), Change(6)]);
+    }
+
+    #[test]
+    fn combinators() {
+        let stream = EventStream::new();
+        let other = EventStream::new();
+        let merged = stream.clone()
+            .filter_map(|msg| match *msg {
+                Change(value) => Some(value),
+                Click => None,
+            })
+            .map(|value| value * 2)
+            .filter(|value| *value > 0)
+            .merge(&other);
+        stream.emit(Change(1));
+        stream.emit(Click);
+        other.emit(10);
+        stream.emit(Change(-3));
+        stream.emit(Change(2));
+        assert_eq!(merged.take(3).wait().map(|msg| msg.expect("message")).collect::<Vec<_>>(), vec![2, 10, 4]);
+    }
+
+    #[test]
+    fn ask() {
+        enum Request {
+            Double(i32, Reply<i32>),
+        }
+
+        let stream = EventStream::new();
+        let response = stream.ask(|reply| Request::Double(21, reply));
+        let dropped_response = stream.ask(|reply| Request::Double(1, reply));
+        for request in stream.clone().take(2).wait() {
+            match request.expect("request") {
+                Request::Double(1, _) => (),
+                Request::Double(value, reply) => reply.reply(value * 2),
+            }
+        }
+        assert_eq!(response.wait(), Ok(42));
+        assert!(dropped_response.wait().is_err());
+    }
+
+    #[test]
+    fn close_callbacks() {
+        let stream: EventStream<Msg> = EventStream::new();
+        let calls = Rc::new(Cell::new(0));
+        {
+            let calls = calls.clone();
+            let closed_stream = stream.clone();
+            stream.on_close(move || {
+                assert!(closed_stream.is_closed());
+                calls.set(calls.get() + 1);
+            });
+        }
+        stream.close().expect("close");
+        stream.close().expect("close");
+        assert_eq!(calls.get(), 1);
+        {
+            let calls = calls.clone();
+            stream.on_close(move || calls.set(calls.get() + 1));
+        }
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn close_after_pending() {
+        let stream = EventStream::new();
+        let closed = Rc::new(Cell::new(false));
+        {
+            let closed = closed.clone();
+            stream.on_close(move || closed.set(true));
+        }
+        stream.emit(Click);
+        stream.close_after_pending().expect("close");
+        assert!(!stream.is_closed());
+        stream.emit(Change(1));
+        assert_eq!(stream.clone().wait().map(|msg| msg.expect("message")).collect::<Vec<_>>(),
+            vec![Click, Change(1)]);
+        assert!(stream.is_closed());
+        assert!(closed.get());
+
+        let stream: EventStream<Msg> = EventStream::new();
+        stream.close_after_pending().expect("close");
+        assert!(stream.is_closed());
+    }
+
+    struct Notified(AtomicBool);
+
+    impl Notify for Notified {
+        fn notify(&self, _id: usize) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn sender_wakes_up_stream() {
+        let stream = EventStream::new();
+        let mut task = executor::spawn(stream.clone());
+        let notified = Arc::new(Notified(AtomicBool::new(false)));
+        assert_eq!(task.poll_stream_notify(&notified, 0), Ok(Async::NotReady));
+        // The sender is created after the stream started waiting.
+        let sender = stream.sender();
+        thread::spawn(move || sender.send(Change(42)).expect("send"))
+            .join().expect("join");
+        assert!(notified.0.load(Ordering::SeqCst));
+        assert_eq!(task.poll_stream_notify(&notified, 0), Ok(Async::Ready(Some(Change(42)))));
+    }
+
+    fn counter(stream: &EventStream<Msg>) -> (Rc<Cell<i32>>, ObserverHandle<Msg>) {
+        let count = Rc::new(Cell::new(0));
+        let handle = {
+            let count = count.clone();
+            stream.observe(move |_| count.set(count.get() + 1))
+        };
+        (count, handle)
+    }
+
+    #[test]
+    fn observer_disconnect() {
+        let stream = EventStream::new();
+        let (count, handle) = counter(&stream);
+        let (other_count, _other_handle) = counter(&stream);
+        stream.emit(Click);
+        handle.disconnect();
+        stream.emit(Click);
+        assert_eq!(count.get(), 1);
+        assert_eq!(other_count.get(), 2);
+        // Disconnecting twice or after the stream is dropped does nothing.
+        handle.disconnect();
+        drop(stream);
+        handle.disconnect();
+    }
+
+    #[test]
+    fn observer_guard() {
+        let stream = EventStream::new();
+        let (count, handle) = counter(&stream);
+        {
+            let _guard = handle.guard();
+            stream.emit(Click);
+        }
+        stream.emit(Click);
+        assert_eq!(count.get(), 1);
+        assert!(stream.stream.borrow().observers.is_empty());
+    }
+
+    #[test]
+    fn observe_weak() {
+        let stream = EventStream::new();
+        let destination = EventStream::new();
+        let _ = stream.observe_weak(&destination, |destination, msg| {
+            if let Change(value) = *msg {
+                destination.emit(Change(value * 2));
+            }
+        });
+        stream.emit(Change(1));
+        assert_eq!(collect(&destination, 1), vec![Change(2)]);
+
+        // The observer removes itself when the destination is closed.
+        destination.close().expect("close stream");
+        stream.emit(Change(2));
+        assert!(stream.stream.borrow().observers.is_empty());
+
+        // And when the destination is dropped.
+        let destination: EventStream<Msg> = EventStream::new();
+        let _ = stream.observe_weak(&destination, |destination, _| destination.emit(Click));
+        assert_eq!(stream.stream.borrow().observers.len(), 1);
+        drop(destination);
+        stream.emit(Click);
+        assert!(stream.stream.borrow().observers.is_empty());
+    }
+
+    #[test]
+    fn notify_observers() {
+        let stream = EventStream::new();
+        let observed = Rc::new(Cell::new(0));
+        {
+            let observed = observed.clone();
+            let _ = stream.observe(move |msg| {
+                if let Change(value) = *msg {
+                    observed.set(value);
+                }
+            });
+        }
+        stream.notify_observers(&Change(1));
+        assert_eq!(observed.get(), 1);
+        assert_eq!(stream.queued_count(), 0);
+        stream.emit(Change(2));
+        assert_eq!(observed.get(), 2);
+        assert_eq!(collect(&stream, 1), vec![Change(2)]);
+    }
+
+    #[test]
+    fn sender_after_drop() {
+        let stream: EventStream<Msg> = EventStream::new();
+        let sender = stream.sender();
+        drop(stream);
+        assert_eq!(sender.send(Click), Err(Click));
+    }
+}
@@ -1,3 +1,5 @@
+#![recursion_limit="256"]
+
 #[macro_use]
 extern crate quote;
 extern crate relm_gen_widget;
@@ -10,17 +12,33 @@ use syn::{
     Generics,
     Ident,
     MacroInput,
+    MetaItem,
+    NestedMetaItem,
+    Variant,
 };
 use syn::Body::Enum;
-use syn::VariantData::Unit;
+use syn::VariantData::{self, Unit};
 
 pub fn impl_msg(ast: &MacroInput, krate: Ident) -> Tokens {
     let display = derive_display_variant(ast, &krate);
     let into_option = derive_into_option(ast, &krate);
+    let serde =
+        if has_serde_attribute(ast) {
+            let serialize = derive_serialize(ast, &krate);
+            let deserialize = derive_deserialize(ast, &krate);
+            quote! {
+                #serialize
+                #deserialize
+            }
+        }
+        else {
+            quote! {}
+        };
 
     quote! {
         #display
         #into_option
+        #serde
     }
 }
 
@@ -152,3 +170,267 @@ fn remove_generic_bounds(generics: &Generics) -> Generics {
     }
     generics.clone()
 }
+
+/// Check whether the message type has the `#[msg(serde)]` attribute.
+fn has_serde_attribute(ast: &MacroInput) -> bool {
+    let serde = NestedMetaItem::MetaItem(MetaItem::Word(Ident::new("serde")));
+    ast.attrs.iter().any(|attr| {
+        match attr.value {
+            MetaItem::List(ref ident, ref items) if ident == "msg" => items.contains(&serde),
+            _ => false,
+        }
+    })
+}
+
+fn get_serde_variants(ast: &MacroInput) -> &[Variant] {
+    if !ast.generics.ty_params.is_empty() || !ast.generics.lifetimes.is_empty() {
+        panic!("#[msg(serde)] is not supported on generic message types");
+    }
+    match ast.body {
+        Enum(ref variants) => variants,
+        _ => panic!("Expected enum but found {:?}", ast.body),
+    }
+}
+
+fn get_field_names(data: &VariantData) -> Vec<Ident> {
+    match *data {
+        VariantData::Struct(ref fields) => fields.iter()
+            .map(|field| field.ident.clone().expect("field name"))
+            .collect(),
+        _ => (0..data.fields().len())
+            .map(|index| Ident::new(format!("__field{}", index)))
+            .collect(),
+    }
+}
+
+/// Serialize the messages like `#[derive(Serialize)]` does for enums.
+fn derive_serialize(ast: &MacroInput, krate: &Ident) -> Tokens {
+    let name = &ast.ident;
+    let name_string = name.to_string();
+    let variants = get_serde_variants(ast);
+    let arms = variants.iter().enumerate().map(|(index, variant)| {
+        let index = index as u32;
+        let ident = &variant.ident;
+        let ident_string = ident.to_string();
+        let fields = &get_field_names(&variant.data);
+        let len = fields.len();
+        match variant.data {
+            VariantData::Unit => quote! {
+                #name::#ident => __serializer.serialize_unit_variant(#name_string, #index, #ident_string),
+            },
+            VariantData::Tuple(_) if len == 1 => quote! {
+                #name::#ident(ref __field0) =>
+                    __serializer.serialize_newtype_variant(#name_string, #index, #ident_string, __field0),
+            },
+            VariantData::Tuple(_) => quote! {
+                #name::#ident(#(ref #fields),*) => {
+                    let mut __state = __serializer.serialize_tuple_variant(#name_string, #index, #ident_string,
+                        #len)?;
+                    #(SerializeTupleVariant::serialize_field(&mut __state, #fields)?;)*
+                    SerializeTupleVariant::end(__state)
+                },
+            },
+            VariantData::Struct(_) => {
+                let field_strings = &fields.iter().map(|field| field.to_string()).collect::<Vec<_>>();
+                quote! {
+                    #name::#ident { #(ref #fields),* } => {
+                        let mut __state = __serializer.serialize_struct_variant(#name_string, #index,
+                            #ident_string, #len)?;
+                        #(SerializeStructVariant::serialize_field(&mut __state, #field_strings, #fields)?;)*
+                        SerializeStructVariant::end(__state)
+                    },
+                }
+            },
+        }
+    });
+
+    quote! {
+        impl ::#krate::serde::Serialize for #name {
+            #[allow(unused_imports)]
+            fn serialize<__S>(&self, __serializer: __S) -> ::std::result::Result<__S::Ok, __S::Error>
+                where __S: ::#krate::serde::Serializer,
+            {
+                use ::#krate::serde::ser::{SerializeStructVariant, SerializeTupleVariant};
+
+                match *self {
+                    #(#arms)*
+                }
+            }
+        }
+    }
+}
+
+/// Deserialize the messages like `#[derive(Deserialize)]` does for enums.
+fn derive_deserialize(ast: &MacroInput, krate: &Ident) -> Tokens {
+    let name = &ast.ident;
+    let name_string = name.to_string();
+    let expecting = format!("enum {}", name);
+    let variants = get_serde_variants(ast);
+    let variant_count = variants.len() as u64;
+    let variant_strings = &variants.iter().map(|variant| variant.ident.to_string()).collect::<Vec<_>>();
+    let variant_indexes = &(0..variants.len() as u32).collect::<Vec<_>>();
+    let arms = variants.iter().enumerate().map(|(index, variant)| {
+        let index = index as u32;
+        let ident = &variant.ident;
+        let expecting = format!("variant {}::{}", name, ident);
+        let fields = &get_field_names(&variant.data);
+        let len = fields.len();
+        match variant.data {
+            VariantData::Unit => quote! {
+                #index => {
+                    VariantAccess::unit_variant(__variant)?;
+                    Ok(#name::#ident)
+                },
+            },
+            VariantData::Tuple(_) if len == 1 => quote! {
+                #index => VariantAccess::newtype_variant(__variant).map(#name::#ident),
+            },
+            VariantData::Tuple(_) => {
+                let visit_seq = gen_visit_seq(&quote! { #name::#ident(#(#fields),*) }, fields);
+                quote! {
+                    #index => {
+                        struct __Visitor;
+
+                        impl<'de> Visitor<'de> for __Visitor {
+                            type Value = #name;
+
+                            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                                formatter.write_str(#expecting)
+                            }
+
+                            #visit_seq
+                        }
+
+                        VariantAccess::tuple_variant(__variant, #len, __Visitor)
+                    },
+                }
+            },
+            VariantData::Struct(_) => {
+                let visit_seq = gen_visit_seq(&quote! { #name::#ident { #(#fields),* } }, fields);
+                let field_strings = &fields.iter().map(|field| field.to_string()).collect::<Vec<_>>();
+                let values = fields;
+                quote! {
+                    #index => {
+                        struct __Visitor;
+
+                        impl<'de> Visitor<'de> for __Visitor {
+                            type Value = #name;
+
+                            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                                formatter.write_str(#expecting)
+                            }
+
+                            #visit_seq
+
+                            fn visit_map<__A>(self, mut __map: __A) -> ::std::result::Result<#name, __A::Error>
+                                where __A: MapAccess<'de>,
+                            {
+                                #(let mut #fields = None;)*
+                                while let Some(__key) = __map.next_key::<String>()? {
+                                    match __key.as_str() {
+                                        #(#field_strings => #fields = Some(__map.next_value()?),)*
+                                        _ => {
+                                            let _: de::IgnoredAny = __map.next_value()?;
+                                        },
+                                    }
+                                }
+                                #(let #fields = #values.ok_or_else(|| de::Error::missing_field(#field_strings))?;)*
+                                Ok(#name::#ident { #(#fields),* })
+                            }
+                        }
+
+                        const FIELDS: &'static [&'static str] = &[#(#field_strings),*];
+                        VariantAccess::struct_variant(__variant, FIELDS, __Visitor)
+                    },
+                }
+            },
+        }
+    });
+
+    quote! {
+        impl<'de> ::#krate::serde::Deserialize<'de> for #name {
+            #[allow(unused_imports)]
+            fn deserialize<__D>(__deserializer: __D) -> ::std::result::Result<Self, __D::Error>
+                where __D: ::#krate::serde::Deserializer<'de>,
+            {
+                use std::fmt;
+
+                use ::#krate::serde::de::{self, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+
+                const VARIANTS: &'static [&'static str] = &[#(#variant_strings),*];
+
+                struct __Variant(u32);
+
+                impl<'de> ::#krate::serde::Deserialize<'de> for __Variant {
+                    fn deserialize<__D>(__deserializer: __D) -> ::std::result::Result<Self, __D::Error>
+                        where __D: ::#krate::serde::Deserializer<'de>,
+                    {
+                        struct __VariantVisitor;
+
+                        impl<'de> Visitor<'de> for __VariantVisitor {
+                            type Value = __Variant;
+
+                            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                                formatter.write_str("variant identifier")
+                            }
+
+                            fn visit_u64<__E: de::Error>(self, value: u64) -> ::std::result::Result<__Variant, __E> {
+                                if value < #variant_count {
+                                    Ok(__Variant(value as u32))
+                                }
+                                else {
+                                    Err(de::Error::invalid_value(de::Unexpected::Unsigned(value), &self))
+                                }
+                            }
+
+                            fn visit_str<__E: de::Error>(self, value: &str) -> ::std::result::Result<__Variant, __E> {
+                                match value {
+                                    #(#variant_strings => Ok(__Variant(#variant_indexes)),)*
+                                    _ => Err(de::Error::unknown_variant(value, VARIANTS)),
+                                }
+                            }
+                        }
+
+                        __deserializer.deserialize_identifier(__VariantVisitor)
+                    }
+                }
+
+                struct __Visitor;
+
+                impl<'de> Visitor<'de> for __Visitor {
+                    type Value = #name;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str(#expecting)
+                    }
+
+                    fn visit_enum<__A>(self, __data: __A) -> ::std::result::Result<#name, __A::Error>
+                        where __A: EnumAccess<'de>,
+                    {
+                        let (__Variant(__index), __variant) = __data.variant()?;
+                        match __index {
+                            #(#arms)*
+                            _ => Err(de::Error::invalid_value(de::Unexpected::Unsigned(__index as u64), &self)),
+                        }
+                    }
+                }
+
+                __deserializer.deserialize_enum(#name_string, VARIANTS, __Visitor)
+            }
+        }
+    }
+}
+
+/// Generate the `visit_seq()` method of a visitor creating `value` from the sequence of its
+/// `fields`.
+fn gen_visit_seq(value: &Tokens, fields: &[Ident]) -> Tokens {
+    let indexes = &(0..fields.len()).collect::<Vec<_>>();
+    quote! {
+        fn visit_seq<__A>(self, mut __seq: __A) -> ::std::result::Result<Self::Value, __A::Error>
+            where __A: SeqAccess<'de>,
+        {
+            #(let #fields = __seq.next_element()?.ok_or_else(|| de::Error::invalid_length(#indexes, &self))?;)*
+            Ok(#value)
+        }
+    }
+}
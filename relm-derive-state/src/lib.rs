@@ -17,7 +17,7 @@ pub fn simple_msg(input: TokenStream) -> TokenStream {
     gen.parse().unwrap()
 }
 
-#[proc_macro_derive(Msg)]
+#[proc_macro_derive(Msg, attributes(msg))]
 pub fn msg(input: TokenStream) -> TokenStream {
     let string = input.to_string();
     let ast = parse_macro_input(&string).unwrap();
@@ -35,7 +35,11 @@ macro_rules! fold_assign {
     ($_self:expr, $lhs:expr, $new_assign:expr) => {{
         let mut statements = vec![];
         let new_statements =
-            if let Field(ref field_expr, ref ident) = $lhs.node {
+            if is_model_path(&$lhs) {
+                // The whole model is replaced: update every property.
+                Some(create_all_stmts($_self.property_map, $_self.msg_map))
+            }
+            else if let Field(ref field_expr, ref ident) = $lhs.node {
                 if is_model_path(field_expr) {
                     Some(create_stmts(ident, $_self.property_map, $_self.msg_map))
                 }
@@ -102,6 +106,21 @@ pub struct Property {
     pub widget_name: Ident,
 }
 
+/*
+ * Create the statements updating every property depending on the model.
+ */
+pub fn create_all_stmts(property_map: &PropertyModelMap, msg_map: &MsgModelMap) -> Vec<Stmt> {
+    let mut idents: Vec<_> = property_map.keys().chain(msg_map.keys()).collect();
+    // Sort to generate the same code on every compilation.
+    idents.sort_by(|ident1, ident2| ident1.as_ref().cmp(ident2.as_ref()));
+    idents.dedup();
+    let mut stmts = vec![];
+    for ident in idents {
+        stmts.append(&mut create_stmts(ident, property_map, msg_map));
+    }
+    stmts
+}
+
 fn create_stmts(ident: &Ident, property_map: &PropertyModelMap, msg_map: &MsgModelMap) -> Vec<Stmt> {
     let mut stmts = vec![];
     stmts.append(&mut create_stmts_for_props(ident, property_map));
@@ -138,15 +157,23 @@ fn create_stmts_for_props(ident: &Ident, property_map: &PropertyModelMap) -> Vec
             let prop_name = Ident::new(format!("set_{}", property.name));
             let mut tokens = Tokens::new();
             tokens.append_all(&[&property.expr]);
+            let setter_name = prop_name.as_ref();
+            // The setter is measured when profiling is enabled.
             let stmt =
                 if property.is_relm_widget {
                     quote! {
-                        { self.#widget_name.#prop_name(#tokens); }
+                        {{
+                            let _profile = ::relm::profile_setter(::std::any::type_name::<Self>(), #setter_name);
+                            self.#widget_name.#prop_name(#tokens);
+                        }}
                     }
                 }
                 else {
                     quote! {
-                        { self.#widget_name.#prop_name(#tokens); }
+                        {{
+                            let _profile = ::relm::profile_setter(::std::any::type_name::<Self>(), #setter_name);
+                            self.#widget_name.#prop_name(#tokens);
+                        }}
                     }
                 };
             let expr = parse_expr(&stmt.parse::<String>().expect("parse::<String>() in create_stmts"))
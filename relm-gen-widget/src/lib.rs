@@ -41,7 +41,7 @@ mod walker;
 
 use std::collections::{HashMap, HashSet};
 
-use adder::{Adder, Message, Property};
+use adder::{Adder, Message, Property, create_all_stmts};
 use gen::gen;
 pub use gen::gen_where_clause;
 use parser::EitherWidget::{Gtk, Relm};
@@ -55,6 +55,7 @@ use syn::{
     ImplItem,
     Mac,
     MethodSig,
+    Pat,
     Path,
     PathSegment,
     Token,
@@ -85,12 +86,15 @@ pub struct Driver {
     msg_model_map: Option<MsgModelMap>,
     msg_type: Option<ImplItem>,
     other_methods: Vec<ImplItem>,
+    persist_items: Vec<ImplItem>,
     properties_model_map: Option<PropertyModelMap>,
+    restart_method: Option<ImplItem>,
     root_method: Option<ImplItem>,
     root_type: Option<ImplItem>,
     root_widget: Option<Ident>,
     root_widget_expr: Option<Tokens>,
     root_widget_type: Option<Tokens>,
+    undo_items: Vec<ImplItem>,
     update_method: Option<ImplItem>,
     view_macro: Option<Mac>,
     widget_model_type: Option<Ty>,
@@ -118,12 +122,15 @@ impl Driver {
             msg_model_map: None,
             msg_type: None,
             other_methods: vec![],
+            persist_items: vec![],
             properties_model_map: None,
+            restart_method: None,
             root_method: None,
             root_type: None,
             root_widget: None,
             root_widget_expr: None,
             root_widget_type: None,
+            undo_items: vec![],
             update_method: None,
             view_macro: None,
             widget_model_type: None,
@@ -203,9 +210,14 @@ impl Driver {
                                 add_model_param(&mut i, &mut self.model_param_type);
                                 update_items.push(i);
                             },
-                            "subscriptions" => update_items.push(i),
-                            "init_view" | "on_add" => new_items.push(i),
-                            "update" => {
+                            "dynamic_subscriptions" | "on_close" | "panic_msg" | "panic_policy" | "subscriptions" =>
+                                update_items.push(i),
+                            "load_state" | "persist_location" | "persist_name" | "save_state" =>
+                                self.persist_items.push(i),
+                            "restart" => self.restart_method = Some(i),
+                            "undo_depth" | "undo_kind" => self.undo_items.push(i),
+                            "init_view" | "on_add" | "on_destroy" | "on_map" | "on_unmap" => new_items.push(i),
+                            "update" | "update_with_commands" => {
                                 self.widget_msg_type = Some(get_second_param_type(&sig));
                                 self.update_method = Some(i)
                             },
@@ -223,12 +235,20 @@ impl Driver {
                     },
                 }
             }
+            if !self.persist_items.is_empty() {
+                if let Some(model) = update_items.iter_mut().find(|item| item.ident == Ident::new("model")) {
+                    add_persist_to_model(model);
+                }
+            }
             let view = self.get_view(&name, &typ);
             if let Some(on_add) = gen_set_child_prop_calls(&view.widget) {
                 new_items.push(on_add);
             }
             self.msg_model_map = Some(view.msg_model_map);
             self.properties_model_map = Some(view.properties_model_map);
+            if let Some(restart) = self.get_restart() {
+                update_items.push(restart);
+            }
             new_items.push(view.item);
             self.widgets.insert(self.root_widget.clone().expect("root widget"),
             self.root_widget_type.clone().expect("root widget type"));
@@ -240,6 +260,8 @@ impl Driver {
             new_items.push(self.get_root());
             let other_methods = self.get_other_methods(&typ, &generics);
             let update_impl = self.update_impl(&typ, &generics, update_items);
+            let persist_impl = self.persist_impl(&name, &typ, &generics);
+            let undo_impl = self.undo_impl(&typ, &generics);
             let item = Impl(unsafety, polarity, generics, path, typ, new_items);
             ast.node = item;
             let container_impl = view.container_impl;
@@ -248,6 +270,8 @@ impl Driver {
                 #ast
                 #container_impl
                 #update_impl
+                #persist_impl
+                #undo_impl
 
                 #other_methods
             }
@@ -311,6 +335,75 @@ impl Driver {
         }
     }
 
+    /*
+     * Get the method used to restart the widget after a panic.
+     * When it is not provided and the model() method does not take a parameter, generate one
+     * resetting the model and updating the view accordingly.
+     */
+    fn get_restart(&mut self) -> Option<ImplItem> {
+        if let Some(mut func) = self.restart_method.take() {
+            self.add_set_property_to_method(&mut func);
+            return Some(func);
+        }
+        let model_param_is_unit =
+            match self.model_param_type {
+                Some(ImplItem { node: Type(Tup(ref types)), .. }) => types.is_empty(),
+                Some(_) => false,
+                None => true,
+            };
+        if model_param_is_unit {
+            let msg_map = self.msg_model_map.as_ref().expect("update method");
+            let property_map = self.properties_model_map.as_ref().expect("update method");
+            let stmts = create_all_stmts(property_map, msg_map);
+            Some(block_to_impl_item(quote! {
+                fn restart(&mut self, relm: &::relm::Relm<Self>) {
+                    self.model = <Self as ::relm::Update>::model(relm, ());
+                    #(#stmts)*
+                }
+            }))
+        }
+        else {
+            None
+        }
+    }
+
+    /*
+     * Implement Persist when the load_state() and save_state() methods are provided (these method
+     * names, as well as persist_location() and persist_name(), are thus reserved in a widget).
+     * The default persist_name() is the name of the widget struct.
+     */
+    fn persist_impl(&mut self, name: &Ident, typ: &Ty, generics: &Generics) -> Tokens {
+        if self.persist_items.is_empty() {
+            return Tokens::new();
+        }
+        if !self.persist_items.iter().any(|item| item.ident == Ident::new("persist_name")) {
+            // NOTE: use the name of the struct, without the module path, so that it does not
+            // change when the widget is moved.
+            let name = name.as_ref().rsplit("::").next().expect("widget name");
+            self.persist_items.push(block_to_impl_item(quote! {
+                fn persist_name() -> String {
+                    #name.to_string()
+                }
+            }));
+        }
+        let where_clause = gen_where_clause(generics);
+        let state_type = self.persist_items.iter()
+            .filter_map(|item| match item.node {
+                Method(ref sig, _) if item.ident == Ident::new("save_state") => Some(get_return_type(sig.clone())),
+                _ => None,
+            })
+            .next()
+            .expect("save_state method");
+        let items = &self.persist_items;
+        quote! {
+            impl #generics ::relm::Persist for #typ #where_clause {
+                type State = #state_type;
+
+                #(#items)*
+            }
+        }
+    }
+
     fn get_root(&mut self) -> ImplItem {
         self.root_method.take().unwrap_or_else(|| {
             let root_widget_expr = self.root_widget_expr.take().expect("root widget expr");
@@ -416,6 +509,37 @@ impl Driver {
         }
     }
 
+    /*
+     * Implement Undoable when the undo_kind() method is provided: the snapshot is the model and
+     * restoring it updates the view.
+     */
+    fn undo_impl(&mut self, typ: &Ty, generics: &Generics) -> Tokens {
+        if self.undo_items.is_empty() {
+            return Tokens::new();
+        }
+        let where_clause = gen_where_clause(generics);
+        let msg_map = self.msg_model_map.as_ref().expect("update method");
+        let property_map = self.properties_model_map.as_ref().expect("update method");
+        let stmts = create_all_stmts(property_map, msg_map);
+        let items = &self.undo_items;
+        quote! {
+            impl #generics ::relm::Undoable for #typ #where_clause {
+                type Snapshot = <Self as ::relm::Update>::Model;
+
+                fn restore(&mut self, snapshot: Self::Snapshot) {
+                    self.model = snapshot;
+                    #(#stmts)*
+                }
+
+                fn snapshot(&self) -> Self::Snapshot {
+                    self.model.clone()
+                }
+
+                #(#items)*
+            }
+        }
+    }
+
     fn update_impl(&mut self, typ: &Ty, generics: &Generics, items: Vec<ImplItem>) -> Tokens {
         let where_clause = gen_where_clause(generics);
 
@@ -431,6 +555,12 @@ impl Driver {
                 #update
                 #(#items)*
             }
+
+            impl #generics ::relm::ModelRef for #typ #where_clause {
+                fn model_ref(&self) -> &Self::Model {
+                    &self.model
+                }
+            }
         }
     }
 }
@@ -461,6 +591,33 @@ fn add_model_param(model_fn: &mut ImplItem, model_param_type: &mut Option<ImplIt
     }
 }
 
+/*
+ * Read the persisted state into the model returned by the model() method.
+ */
+fn add_persist_to_model(model_fn: &mut ImplItem) {
+    let new_model_fn =
+        if let Method(ref method_sig, ref block) = model_fn.node {
+            let (relm_pat, relm_type) = get_param(method_sig, 0);
+            let (param_pat, param_type) = get_param(method_sig, 1);
+            let return_type = get_return_type(method_sig.clone());
+            block_to_impl_item(quote! {
+                fn model(__relm: #relm_type, __model_param: #param_type) -> #return_type {
+                    let mut model = {
+                        let #relm_pat = __relm;
+                        let #param_pat = __model_param;
+                        #block
+                    };
+                    __relm.persist(&mut model);
+                    model
+                }
+            })
+        }
+        else {
+            return;
+        };
+    model_fn.node = new_model_fn.node;
+}
+
 fn block_to_impl_item(tokens: Tokens) -> ImplItem {
     let implementation = quote! {
         impl Test {
@@ -556,6 +713,15 @@ fn get_return_type(sig: MethodSig) -> Ty {
     }
 }
 
+fn get_param(sig: &MethodSig, index: usize) -> (Pat, Ty) {
+    if let Captured(ref pat, ref typ) = sig.decl.inputs[index] {
+        (pat.clone(), typ.clone())
+    }
+    else {
+        panic!("Unexpected `{:?}`, expecting Captured Ty", sig.decl.inputs[index]);
+    }
+}
+
 fn get_second_param_type(sig: &MethodSig) -> Ty {
     if let Captured(_, ref path) = sig.decl.inputs[1] {
         path.clone()
@@ -0,0 +1,99 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use futures::{Async, Future, Poll};
+use futures::task::{self, Task};
+
+struct Inner {
+    aborted: Cell<bool>,
+    finished: Cell<bool>,
+    task: RefCell<Option<Task>>,
+}
+
+/// A handle to abort a future spawned by a [`Relm`](struct.Relm.html).
+#[derive(Clone)]
+pub struct AbortHandle {
+    inner: Rc<Inner>,
+}
+
+impl AbortHandle {
+    /// Abort the future: it will be dropped without being polled again.
+    pub fn abort(&self) {
+        self.inner.aborted.set(true);
+        if let Some(ref task) = *self.inner.task.borrow() {
+            task.notify();
+        }
+    }
+
+    /// Check whether the future was aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.inner.aborted.get()
+    }
+
+    /// Check whether the future was dropped, because it completed or was aborted.
+    pub fn is_finished(&self) -> bool {
+        self.inner.finished.get()
+    }
+}
+
+/// A future which completes as soon as it is aborted.
+pub struct Abortable<FUTURE> {
+    future: FUTURE,
+    inner: Rc<Inner>,
+}
+
+impl<FUTURE> Drop for Abortable<FUTURE> {
+    fn drop(&mut self) {
+        self.inner.finished.set(true);
+    }
+}
+
+impl<FUTURE: Future<Item=(), Error=()>> Future for Abortable<FUTURE> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.inner.aborted.get() {
+            return Ok(Async::Ready(()));
+        }
+        *self.inner.task.borrow_mut() = Some(task::current());
+        self.future.poll()
+    }
+}
+
+pub fn abortable<FUTURE: Future<Item=(), Error=()>>(future: FUTURE) -> (Abortable<FUTURE>, AbortHandle) {
+    let inner = Rc::new(Inner {
+        aborted: Cell::new(false),
+        finished: Cell::new(false),
+        task: RefCell::new(None),
+    });
+    let handle = AbortHandle {
+        inner: inner.clone(),
+    };
+    let future = Abortable {
+        future,
+        inner,
+    };
+    (future, handle)
+}
@@ -0,0 +1,97 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use relm_core::EventStream;
+
+use into::IntoOption;
+
+// Return false when the subscribed stream is closed.
+type Subscriber<TOPIC> = Rc<Fn(&TOPIC) -> bool>;
+
+thread_local! {
+    static SUBSCRIBERS: RefCell<HashMap<TypeId, Box<Any>>> = RefCell::new(HashMap::new());
+}
+
+fn with_subscribers<CALLBACK, RET, TOPIC>(callback: CALLBACK) -> RET
+    where CALLBACK: FnOnce(&mut Vec<Subscriber<TOPIC>>) -> RET,
+          TOPIC: 'static,
+{
+    SUBSCRIBERS.with(|subscribers| {
+        let mut subscribers = subscribers.borrow_mut();
+        let subscribers = subscribers.entry(TypeId::of::<TOPIC>())
+            .or_insert_with(|| Box::new(Vec::<Subscriber<TOPIC>>::new()));
+        callback(subscribers.downcast_mut().expect("subscribers of topic"))
+    })
+}
+
+/// Send `topic` to every stream subscribed to the `TOPIC` type.
+pub fn publish<TOPIC: 'static>(topic: &TOPIC) {
+    // NOTE: the subscribers are cloned because they can publish or subscribe as well.
+    let subscribers = with_subscribers(|subscribers: &mut Vec<Subscriber<TOPIC>>| subscribers.clone());
+    let closed: Vec<_> = subscribers.into_iter()
+        .filter(|subscriber| !subscriber(topic))
+        .collect();
+    if !closed.is_empty() {
+        with_subscribers(|subscribers: &mut Vec<Subscriber<TOPIC>>| {
+            subscribers.retain(|subscriber| !closed.iter().any(|closed| Rc::ptr_eq(subscriber, closed)));
+        });
+    }
+}
+
+/// Send the message returned by `callback` to `stream` every time a `TOPIC` is published.
+/// The subscription is removed when `stream` is closed.
+pub fn subscribe<CALLBACK, MSG, RET, TOPIC>(stream: &EventStream<MSG>, callback: CALLBACK)
+    where CALLBACK: Fn(&TOPIC) -> RET + 'static,
+          MSG: 'static,
+          RET: IntoOption<MSG>,
+          TOPIC: 'static,
+{
+    let weak_stream = stream.downgrade();
+    let subscriber: Subscriber<TOPIC> = Rc::new(move |topic| {
+        match weak_stream.upgrade() {
+            Some(ref stream) if !stream.is_closed() => {
+                if let Some(msg) = callback(topic).into_option() {
+                    stream.emit(msg);
+                }
+                true
+            },
+            _ => false,
+        }
+    });
+    let weak_subscriber = Rc::downgrade(&subscriber);
+    with_subscribers(|subscribers| subscribers.push(subscriber));
+    // NOTE: remove the subscriber right away to drop its callback (and what it captures).
+    stream.on_close(move || {
+        if let Some(subscriber) = weak_subscriber.upgrade() {
+            // NOTE: the subscribers might already be destroyed if the stream is closed when the thread exits.
+            let _ = SUBSCRIBERS.try_with(|_| {
+                with_subscribers(|subscribers: &mut Vec<Subscriber<TOPIC>>| {
+                    subscribers.retain(|other| !Rc::ptr_eq(other, &subscriber));
+                });
+            });
+        }
+    });
+}
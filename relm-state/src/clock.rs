@@ -0,0 +1,207 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll, Stream};
+use futures::task::{self, Task};
+#[cfg(feature = "glib")]
+use futures_glib::{Interval, Timeout};
+
+#[cfg(not(feature = "glib"))]
+use executor::local_executor;
+
+#[cfg(feature = "glib")]
+thread_local! {
+    static CLOCK: RefCell<Rc<Clock>> = RefCell::new(Rc::new(GlibClock));
+}
+
+#[cfg(not(feature = "glib"))]
+thread_local! {
+    static CLOCK: RefCell<Rc<Clock>> = RefCell::new(Rc::new(local_executor()));
+}
+
+/// A source of timers used by relm.
+///
+/// The default clock uses the glib main loop (or the
+/// [`local_executor()`](fn.local_executor.html) when the `glib` feature is disabled), but it can
+/// be replaced by a
+/// [`VirtualClock`](struct.VirtualClock.html) with [`set_clock()`](fn.set_clock.html) in tests.
+pub trait Clock {
+    /// Get the current time of this clock.
+    fn now(&self) -> Instant;
+
+    /// Create a future completing at `instant`.
+    fn delay_until(&self, instant: Instant) -> Box<Future<Item=(), Error=()>>;
+
+    /// Create a stream producing a value every `duration`.
+    fn interval(&self, duration: Duration) -> Box<Stream<Item=(), Error=()>>;
+}
+
+/// A clock using the timers of the glib main loop.
+#[cfg(feature = "glib")]
+pub struct GlibClock;
+
+#[cfg(feature = "glib")]
+impl Clock for GlibClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn delay_until(&self, instant: Instant) -> Box<Future<Item=(), Error=()>> {
+        let now = Instant::now();
+        let duration =
+            if instant > now {
+                instant - now
+            }
+            else {
+                Duration::from_secs(0)
+            };
+        Box::new(Timeout::new(duration).map_err(|_| ()))
+    }
+
+    fn interval(&self, duration: Duration) -> Box<Stream<Item=(), Error=()>> {
+        Box::new(Interval::new(duration).map_err(|_| ()))
+    }
+}
+
+struct VirtualClockInner {
+    now: Cell<Instant>,
+    tasks: RefCell<Vec<Task>>,
+}
+
+/// A clock whose time only moves forward when [`advance()`](#method.advance) is called.
+///
+/// This allows testing components using timers without waiting.
+#[derive(Clone)]
+pub struct VirtualClock {
+    inner: Rc<VirtualClockInner>,
+}
+
+impl VirtualClock {
+    /// Create a new virtual clock starting at the current time.
+    pub fn new() -> Self {
+        VirtualClock {
+            inner: Rc::new(VirtualClockInner {
+                now: Cell::new(Instant::now()),
+                tasks: RefCell::new(vec![]),
+            }),
+        }
+    }
+
+    /// Move the time forward by `duration`, waking up the expired timers.
+    pub fn advance(&self, duration: Duration) {
+        self.inner.now.set(self.inner.now.get() + duration);
+        let tasks: Vec<_> = self.inner.tasks.borrow_mut().drain(..).collect();
+        for task in tasks {
+            task.notify();
+        }
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        VirtualClock::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.inner.now.get()
+    }
+
+    fn delay_until(&self, instant: Instant) -> Box<Future<Item=(), Error=()>> {
+        Box::new(VirtualDelay {
+            clock: self.inner.clone(),
+            deadline: instant,
+        })
+    }
+
+    fn interval(&self, duration: Duration) -> Box<Stream<Item=(), Error=()>> {
+        Box::new(VirtualInterval {
+            clock: self.inner.clone(),
+            deadline: self.inner.now.get() + duration,
+            duration,
+        })
+    }
+}
+
+struct VirtualDelay {
+    clock: Rc<VirtualClockInner>,
+    deadline: Instant,
+}
+
+impl Future for VirtualDelay {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.clock.now.get() >= self.deadline {
+            Ok(Async::Ready(()))
+        }
+        else {
+            self.clock.tasks.borrow_mut().push(task::current());
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+struct VirtualInterval {
+    clock: Rc<VirtualClockInner>,
+    deadline: Instant,
+    duration: Duration,
+}
+
+impl Stream for VirtualInterval {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.clock.now.get() >= self.deadline {
+            self.deadline += self.duration;
+            Ok(Async::Ready(Some(())))
+        }
+        else {
+            self.clock.tasks.borrow_mut().push(task::current());
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// Get the clock used by relm on the current thread.
+pub fn clock() -> Rc<Clock> {
+    CLOCK.with(|clock| clock.borrow().clone())
+}
+
+/// Set the clock used by relm on the current thread.
+/// It is only used by the timers created after this call.
+pub fn set_clock<CLOCK: Clock + 'static>(clock: CLOCK) {
+    CLOCK.with(|current_clock| *current_clock.borrow_mut() = Rc::new(clock));
+}
+
+/// Create a future completing after `duration` using the clock of the current thread.
+pub fn timeout(duration: Duration) -> Box<Future<Item=(), Error=()>> {
+    let clock = clock();
+    let instant = clock.now() + duration;
+    clock.delay_until(instant)
+}
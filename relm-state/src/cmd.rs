@@ -0,0 +1,84 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::time::Duration;
+
+use futures::Future;
+
+use {Relm, Update};
+
+/// A command returned by [`Update::update_with_commands()`](trait.Update.html#method.update_with_commands).
+///
+/// A command describes a side effect to be executed by relm after the update, so that the update
+/// logic can be tested without a main loop.
+pub enum Cmd<MSG> {
+    /// Do nothing.
+    None,
+    /// Send a message to the component.
+    Msg(MSG),
+    /// Spawn a future sending the resulting message to the component, whether it succeeds or fails.
+    Future(Box<Future<Item=MSG, Error=MSG>>),
+    /// Send a message to the component after a duration.
+    Timeout(Duration, MSG),
+    /// Execute multiple commands.
+    Batch(Vec<Cmd<MSG>>),
+}
+
+impl<MSG: 'static> Cmd<MSG> {
+    /// Create a command spawning `future` and sending the message `callback` in case of success
+    /// and `failure_callback` in case of failure.
+    pub fn perform<CALLBACK, FAILCALLBACK, FUTURE>(future: FUTURE, callback: CALLBACK, failure_callback: FAILCALLBACK)
+            -> Self
+        where CALLBACK: FnOnce(FUTURE::Item) -> MSG + 'static,
+              FAILCALLBACK: FnOnce(FUTURE::Error) -> MSG + 'static,
+              FUTURE: Future + 'static,
+    {
+        Cmd::Future(Box::new(future.map(callback).map_err(failure_callback)))
+    }
+
+    /// Execute the command, sending the resulting messages to the component of `relm`.
+    /// The spawned futures are aborted when this component is destroyed.
+    pub fn execute<UPDATE: Update<Msg=MSG>>(self, relm: &Relm<UPDATE>) {
+        let stream = relm.stream();
+        match self {
+            Cmd::None => (),
+            Cmd::Msg(msg) => stream.emit(msg),
+            Cmd::Future(future) => {
+                let stream = stream.clone();
+                let future = future.then(move |result| {
+                    match result {
+                        Ok(msg) | Err(msg) => stream.emit(msg),
+                    }
+                    Ok(())
+                });
+                let _ = relm.exec(future);
+            },
+            Cmd::Timeout(duration, msg) => {
+                let _ = relm.timeout(duration, msg);
+            },
+            Cmd::Batch(commands) => {
+                for command in commands {
+                    command.execute(relm);
+                }
+            },
+        }
+    }
+}
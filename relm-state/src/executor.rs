@@ -0,0 +1,452 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Executors running the futures of the components.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll, Stream};
+use futures::executor::{self, Notify};
+use futures::future::{self, ExecuteError};
+use futures::task::{self, Task};
+#[cfg(feature = "glib")]
+use futures_glib::{self, MainContext, Source, SourceFuncs};
+#[cfg(feature = "glib")]
+use glib_sys;
+
+use clock::Clock;
+
+type BoxFuture = Box<Future<Item=(), Error=()>>;
+
+thread_local! {
+    static LOCAL_EXECUTOR: LocalExecutor = LocalExecutor::new();
+}
+
+/// A backend running futures, used by an [`Executor`](struct.Executor.html).
+pub trait Spawner {
+    /// Run `future` until it completes.
+    fn spawn(&self, future: BoxFuture);
+}
+
+#[cfg(feature = "glib")]
+impl Spawner for futures_glib::Executor {
+    fn spawn(&self, future: BoxFuture) {
+        // NOTE: no error can be returned from execute(), hence unwrap().
+        future::Executor::execute(self, future).unwrap();
+    }
+}
+
+/// Handle to the backend running the futures of the components.
+#[derive(Clone)]
+pub struct Executor {
+    spawner: Rc<Spawner>,
+}
+
+impl Executor {
+    /// Create an executor running its futures with `spawner`.
+    pub fn new<SPAWNER: Spawner + 'static>(spawner: SPAWNER) -> Self {
+        Executor {
+            spawner: Rc::new(spawner),
+        }
+    }
+}
+
+impl<FUTURE> future::Executor<FUTURE> for Executor
+    where FUTURE: Future<Item=(), Error=()> + 'static,
+{
+    fn execute(&self, future: FUTURE) -> Result<(), ExecuteError<FUTURE>> {
+        self.spawner.spawn(Box::new(future));
+        Ok(())
+    }
+}
+
+/// A [`Spawner`](trait.Spawner.html) running its futures from a glib source attached to the default
+/// main context.
+///
+/// The priority of this source (e.g. `glib::PRIORITY_DEFAULT_IDLE`) decides whether the futures of
+/// the components, including the one dispatching their messages, run before or after the other
+/// glib sources, like the redraws of gtk.
+#[cfg(feature = "glib")]
+pub struct GlibSpawner {
+    executor: LocalExecutor,
+    source: Source<GlibSource>,
+}
+
+#[cfg(feature = "glib")]
+impl GlibSpawner {
+    /// Create a spawner whose glib source has the specified `priority`.
+    pub fn new(priority: i32) -> Self {
+        let cx = MainContext::default(|cx| cx.clone());
+        let executor = LocalExecutor::with_context(cx.clone());
+        let source = Source::new(GlibSource {
+            executor: executor.clone(),
+        });
+        source.set_priority(priority);
+        source.attach(&cx);
+        GlibSpawner {
+            executor,
+            source,
+        }
+    }
+
+    /// Get the priority of the glib source.
+    pub fn priority(&self) -> i32 {
+        self.source.priority()
+    }
+
+    /// Change the priority of the glib source.
+    pub fn set_priority(&self, priority: i32) {
+        self.source.set_priority(priority);
+    }
+}
+
+#[cfg(feature = "glib")]
+impl Drop for GlibSpawner {
+    fn drop(&mut self) {
+        // NOTE: the main context keeps a reference to the source, so remove it to drop the futures.
+        self.source.destroy();
+    }
+}
+
+#[cfg(feature = "glib")]
+impl Spawner for GlibSpawner {
+    fn spawn(&self, future: BoxFuture) {
+        self.executor.spawn(future);
+    }
+}
+
+#[cfg(feature = "glib")]
+struct GlibSource {
+    executor: LocalExecutor,
+}
+
+#[cfg(feature = "glib")]
+impl SourceFuncs for GlibSource {
+    type CallbackArg = ();
+
+    fn prepare(&self, _source: &Source<Self>) -> (bool, Option<Duration>) {
+        let timeout = self.executor.next_timer()
+            .map(|deadline| {
+                let now = (self.executor.now)();
+                if deadline > now {
+                    deadline - now
+                }
+                else {
+                    Duration::from_secs(0)
+                }
+            });
+        (self.executor.is_ready(), timeout)
+    }
+
+    fn check(&self, _source: &Source<Self>) -> bool {
+        self.executor.is_ready()
+    }
+
+    fn dispatch(&self, _source: &Source<Self>, _func: glib_sys::GSourceFunc, _data: glib_sys::gpointer) -> bool {
+        self.executor.run_until_stalled();
+        true
+    }
+
+    fn g_source_func<F>() -> glib_sys::GSourceFunc
+        where F: FnMut(()) -> bool,
+    {
+        // NOTE: this source has no callback.
+        None
+    }
+}
+
+struct Ready {
+    condvar: Condvar,
+    // NOTE: the main context to wake up when a future spawned by a GlibSpawner is notified from
+    // another thread.
+    #[cfg(feature = "glib")]
+    context: Option<MainContext>,
+    ids: Mutex<VecDeque<usize>>,
+}
+
+impl Notify for Ready {
+    fn notify(&self, id: usize) {
+        self.ids.lock().expect("ready lock").push_back(id);
+        self.condvar.notify_one();
+        #[cfg(feature = "glib")]
+        {
+            if let Some(ref context) = self.context {
+                context.wakeup();
+            }
+        }
+    }
+}
+
+struct Inner {
+    next_id: usize,
+    tasks: HashMap<usize, executor::Spawn<BoxFuture>>,
+}
+
+type Now = Rc<Fn() -> Instant>;
+
+// NOTE: the timers are not in Inner to avoid a reference cycle between the tasks and the executor.
+type Timers = Rc<RefCell<Vec<(Instant, Task)>>>;
+
+/// A single-threaded executor which does not depend on glib.
+///
+/// The futures only run when [`run()`](#method.run) or
+/// [`run_until_stalled()`](#method.run_until_stalled) is called on the thread where they were
+/// spawned. This executor is also a [`Clock`](trait.Clock.html) whose timers are fired by these
+/// methods.
+#[derive(Clone)]
+pub struct LocalExecutor {
+    inner: Rc<RefCell<Inner>>,
+    now: Now,
+    ready: Arc<Ready>,
+    timers: Timers,
+}
+
+impl LocalExecutor {
+    /// Create a new executor whose timers use the system time.
+    pub fn new() -> Self {
+        Self::with_now(Rc::new(Instant::now))
+    }
+
+    /// Create a new executor whose timers use the time of `clock`.
+    /// With a [`VirtualClock`](struct.VirtualClock.html), the timers are fired by
+    /// [`run_until_stalled()`](#method.run_until_stalled) once the clock was advanced past their
+    /// deadline.
+    /// Since [`run()`](#method.run) waits for the system time to reach the deadline of the next
+    /// timer, it should not be used when timers are pending on such a clock.
+    pub fn with_clock<CLOCK: Clock + 'static>(clock: CLOCK) -> Self {
+        Self::with_now(Rc::new(move || clock.now()))
+    }
+
+    #[cfg(feature = "glib")]
+    fn with_context(context: MainContext) -> Self {
+        Self::with_ready(Rc::new(Instant::now), Ready {
+            condvar: Condvar::new(),
+            context: Some(context),
+            ids: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    fn with_now(now: Now) -> Self {
+        Self::with_ready(now, Ready {
+            condvar: Condvar::new(),
+            #[cfg(feature = "glib")]
+            context: None,
+            ids: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    fn with_ready(now: Now, ready: Ready) -> Self {
+        LocalExecutor {
+            inner: Rc::new(RefCell::new(Inner {
+                next_id: 0,
+                tasks: HashMap::new(),
+            })),
+            now,
+            ready: Arc::new(ready),
+            timers: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    /// Get an `Executor` spawning its futures on this executor.
+    pub fn executor(&self) -> Executor {
+        Executor::new(self.clone())
+    }
+
+    /// Check if no future is running.
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().tasks.is_empty()
+    }
+
+    /// Run the futures until they all complete, waiting for the timers and for the messages sent
+    /// from other threads.
+    pub fn run(&self) {
+        loop {
+            self.run_until_stalled();
+            if self.is_empty() {
+                return;
+            }
+            let next_timer = self.next_timer();
+            let ids = self.ready.ids.lock().expect("ready lock");
+            if ids.is_empty() {
+                match next_timer {
+                    Some(deadline) => {
+                        let now = (self.now)();
+                        if deadline > now {
+                            let _ = self.ready.condvar.wait_timeout(ids, deadline - now).expect("ready lock");
+                        }
+                    },
+                    None => {
+                        drop(self.ready.condvar.wait(ids).expect("ready lock"));
+                    },
+                }
+            }
+        }
+    }
+
+    /// Run the futures which can make progress and fire the expired timers, without waiting.
+    pub fn run_until_stalled(&self) {
+        loop {
+            self.fire_timers();
+            let id = self.ready.ids.lock().expect("ready lock").pop_front();
+            match id {
+                Some(id) => self.poll(id),
+                None => return,
+            }
+        }
+    }
+
+    // Check if a future was notified or a timer expired.
+    #[cfg(feature = "glib")]
+    fn is_ready(&self) -> bool {
+        let now = (self.now)();
+        !self.ready.ids.lock().expect("ready lock").is_empty() ||
+            self.next_timer().is_some_and(|deadline| deadline <= now)
+    }
+
+    fn next_timer(&self) -> Option<Instant> {
+        self.timers.borrow().iter()
+            .map(|&(deadline, _)| deadline)
+            .min()
+    }
+
+    fn fire_timers(&self) {
+        let now = (self.now)();
+        let expired: Vec<_> = {
+            let mut timers = self.timers.borrow_mut();
+            let (expired, pending) = timers.drain(..).partition(|&(deadline, _)| deadline <= now);
+            *timers = pending;
+            expired
+        };
+        for (_, task) in expired {
+            task.notify();
+        }
+    }
+
+    fn poll(&self, id: usize) {
+        // NOTE: take the task out so that the future can spawn other futures.
+        let task = self.inner.borrow_mut().tasks.remove(&id);
+        if let Some(mut task) = task {
+            match task.poll_future_notify(&self.ready, id) {
+                Ok(Async::NotReady) => {
+                    let _ = self.inner.borrow_mut().tasks.insert(id, task);
+                },
+                Ok(Async::Ready(())) | Err(()) => (),
+            }
+        }
+    }
+}
+
+impl Default for LocalExecutor {
+    fn default() -> Self {
+        LocalExecutor::new()
+    }
+}
+
+impl Spawner for LocalExecutor {
+    fn spawn(&self, future: BoxFuture) {
+        let id = {
+            let mut inner = self.inner.borrow_mut();
+            let id = inner.next_id;
+            inner.next_id += 1;
+            let _ = inner.tasks.insert(id, executor::spawn(future));
+            id
+        };
+        self.ready.notify(id);
+    }
+}
+
+impl Clock for LocalExecutor {
+    fn now(&self) -> Instant {
+        (self.now)()
+    }
+
+    fn delay_until(&self, instant: Instant) -> Box<Future<Item=(), Error=()>> {
+        Box::new(LocalDelay {
+            deadline: instant,
+            now: self.now.clone(),
+            timers: self.timers.clone(),
+        })
+    }
+
+    fn interval(&self, duration: Duration) -> Box<Stream<Item=(), Error=()>> {
+        Box::new(LocalInterval {
+            deadline: (self.now)() + duration,
+            duration,
+            now: self.now.clone(),
+            timers: self.timers.clone(),
+        })
+    }
+}
+
+struct LocalDelay {
+    deadline: Instant,
+    now: Now,
+    timers: Timers,
+}
+
+impl Future for LocalDelay {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if (self.now)() >= self.deadline {
+            Ok(Async::Ready(()))
+        }
+        else {
+            self.timers.borrow_mut().push((self.deadline, task::current()));
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+struct LocalInterval {
+    deadline: Instant,
+    duration: Duration,
+    now: Now,
+    timers: Timers,
+}
+
+impl Stream for LocalInterval {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if (self.now)() >= self.deadline {
+            self.deadline += self.duration;
+            Ok(Async::Ready(Some(())))
+        }
+        else {
+            self.timers.borrow_mut().push((self.deadline, task::current()));
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// Get the `LocalExecutor` of the current thread, used by
+/// [`create_executor()`](fn.create_executor.html) and as the default clock when the `glib`
+/// feature is disabled.
+pub fn local_executor() -> LocalExecutor {
+    LOCAL_EXECUTOR.with(|executor| executor.clone())
+}
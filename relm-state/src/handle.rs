@@ -0,0 +1,120 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use futures::{Async, Future, Poll};
+use futures::sync::oneshot;
+use relm_core::EventStream;
+
+use Update;
+
+/// Read-only access to the model of a component.
+///
+/// The `#[widget]` attribute implements this trait.
+pub trait ModelRef: Update {
+    /// Get the model of this component.
+    fn model_ref(&self) -> &Self::Model;
+}
+
+/// Handle to a bare component created by [`execute()`](fn.execute.html).
+pub struct ComponentHandle<UPDATE: Update> {
+    component: Rc<RefCell<UPDATE>>,
+    stream: EventStream<UPDATE::Msg>,
+}
+
+impl<UPDATE: Update> Clone for ComponentHandle<UPDATE> {
+    fn clone(&self) -> Self {
+        ComponentHandle {
+            component: self.component.clone(),
+            stream: self.stream.clone(),
+        }
+    }
+}
+
+impl<UPDATE: Update> ComponentHandle<UPDATE> {
+    #[doc(hidden)]
+    pub fn new(stream: EventStream<UPDATE::Msg>, component: Rc<RefCell<UPDATE>>) -> Self {
+        ComponentHandle {
+            component,
+            stream,
+        }
+    }
+
+    /// Close the stream of the component: the messages sent afterwards are ignored.
+    pub fn close(&self) {
+        let _ = self.stream.close();
+    }
+
+    /// Create a future which completes when the stream of the component is closed.
+    pub fn closed(&self) -> Closed {
+        let (sender, receiver) = oneshot::channel();
+        let sender = RefCell::new(Some(sender));
+        self.stream.on_close(move || {
+            if let Some(sender) = sender.borrow_mut().take() {
+                let _ = sender.send(());
+            }
+        });
+        Closed {
+            receiver,
+        }
+    }
+
+    /// Send a message to the component.
+    pub fn emit(&self, msg: UPDATE::Msg) {
+        self.stream.emit(msg);
+    }
+
+    /// Get the event stream of the component.
+    pub fn stream(&self) -> &EventStream<UPDATE::Msg> {
+        &self.stream
+    }
+
+    /// Call `callback` with the model of the component.
+    ///
+    /// ## Panics
+    /// Panics when called from the `update()` method of this component.
+    pub fn with_model<CALLBACK, VALUE>(&self, callback: CALLBACK) -> VALUE
+        where CALLBACK: FnOnce(&UPDATE::Model) -> VALUE,
+              UPDATE: ModelRef,
+    {
+        callback(self.component.borrow().model_ref())
+    }
+}
+
+/// Future which completes when the stream of a component is closed.
+pub struct Closed {
+    receiver: oneshot::Receiver<()>,
+}
+
+impl Future for Closed {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.receiver.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            // The sender is dropped when the stream is dropped, which also terminates it.
+            Ok(Async::Ready(())) | Err(_) => Ok(Async::Ready(())),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Time-travel debugging: keep the states of a component after every message.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use Update;
+use inspector::Inspector;
+
+struct Inner<MSG, SNAPSHOT> {
+    capacity: usize,
+    // Set when a method to travel in the history is called while the current message is handled,
+    // so that the messages used to travel are not recorded, even when the position did not change.
+    moved: bool,
+    pending: Option<MSG>,
+    position: usize,
+    // The first state is the state before the first recorded message.
+    states: VecDeque<(Option<MSG>, SNAPSHOT)>,
+}
+
+/// The states of a component after every message it handled, used to step backward and forward
+/// in time.
+///
+/// A `History` is created by [`Relm::time_travel()`](struct.Relm.html#method.time_travel).
+/// Assign the snapshot returned by [`back()`](#method.back), [`forward()`](#method.forward) or
+/// [`goto()`](#method.goto) to the model in `update()`: for a `#[widget]`, assigning `self.model`
+/// updates the whole view.
+/// When a message is handled while in the past, the states after the current one are discarded.
+pub struct History<MSG, SNAPSHOT> {
+    inner: Rc<RefCell<Inner<MSG, SNAPSHOT>>>,
+}
+
+impl<MSG, SNAPSHOT> Clone for History<MSG, SNAPSHOT> {
+    fn clone(&self) -> Self {
+        History {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<MSG: Clone, SNAPSHOT: Clone> History<MSG, SNAPSHOT> {
+    /// Create a history keeping at most `capacity` states.
+    pub fn new(capacity: usize) -> Self {
+        History {
+            inner: Rc::new(RefCell::new(Inner {
+                capacity: capacity.max(1),
+                moved: false,
+                pending: None,
+                position: 0,
+                states: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Go to the previous state, returning it.
+    pub fn back(&self) -> Option<SNAPSHOT> {
+        let position = self.position();
+        if position == 0 {
+            self.inner.borrow_mut().moved = true;
+            return None;
+        }
+        self.goto(position - 1)
+    }
+
+    /// Remove all the states.
+    pub fn clear(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.states.clear();
+        inner.position = 0;
+    }
+
+    /// Go to the next state, returning it.
+    pub fn forward(&self) -> Option<SNAPSHOT> {
+        self.goto(self.position() + 1)
+    }
+
+    /// Go to the state at `index`, returning it.
+    pub fn goto(&self, index: usize) -> Option<SNAPSHOT> {
+        let mut inner = self.inner.borrow_mut();
+        let snapshot = inner.states.get(index).map(|(_, snapshot)| snapshot.clone());
+        inner.moved = true;
+        if snapshot.is_some() {
+            inner.position = index;
+        }
+        snapshot
+    }
+
+    /// Check if the current state is not the last one.
+    pub fn is_traveling(&self) -> bool {
+        let inner = self.inner.borrow();
+        inner.position + 1 < inner.states.len()
+    }
+
+    /// Check if no state was recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().states.is_empty()
+    }
+
+    /// Get the number of states.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().states.len()
+    }
+
+    /// Get the message which led to the state at `index`.
+    /// The first state has no message.
+    pub fn message(&self, index: usize) -> Option<MSG> {
+        self.inner.borrow().states.get(index).and_then(|(msg, _)| msg.clone())
+    }
+
+    /// Get the index of the current state.
+    pub fn position(&self) -> usize {
+        self.inner.borrow().position
+    }
+
+    /// Get the state at `index`, without going to it.
+    pub fn snapshot(&self, index: usize) -> Option<SNAPSHOT> {
+        self.inner.borrow().states.get(index).map(|(_, snapshot)| snapshot.clone())
+    }
+
+    fn record(&self, msg: Option<MSG>, snapshot: SNAPSHOT) {
+        let mut inner = self.inner.borrow_mut();
+        let len = inner.position + 1;
+        inner.states.truncate(len);
+        inner.states.push_back((msg, snapshot));
+        while inner.states.len() > inner.capacity {
+            let _ = inner.states.pop_front();
+        }
+        inner.position = inner.states.len() - 1;
+    }
+}
+
+/// Inspector recording the state of the component in a `History`.
+pub struct HistoryInspector<MSG, SNAPSHOT, UPDATE> {
+    history: History<MSG, SNAPSHOT>,
+    snapshot: Box<Fn(&UPDATE) -> SNAPSHOT>,
+}
+
+impl<MSG, SNAPSHOT, UPDATE> HistoryInspector<MSG, SNAPSHOT, UPDATE> {
+    /// Create an inspector recording the result of `snapshot` in `history`.
+    pub fn new<FUNC>(history: History<MSG, SNAPSHOT>, snapshot: FUNC) -> Self
+        where FUNC: Fn(&UPDATE) -> SNAPSHOT + 'static,
+    {
+        HistoryInspector {
+            history,
+            snapshot: Box::new(snapshot),
+        }
+    }
+}
+
+impl<SNAPSHOT, UPDATE> Inspector<UPDATE> for HistoryInspector<UPDATE::Msg, SNAPSHOT, UPDATE>
+    where SNAPSHOT: Clone,
+          UPDATE: Update,
+          UPDATE::Msg: Clone,
+{
+    fn before(&self, component: &mut UPDATE, msg: &UPDATE::Msg) {
+        if self.history.is_empty() {
+            self.history.record(None, (self.snapshot)(component));
+        }
+        let mut inner = self.history.inner.borrow_mut();
+        inner.moved = false;
+        inner.pending = Some(msg.clone());
+    }
+
+    fn after(&self, component: &UPDATE) {
+        let (moved, msg) = {
+            let mut inner = self.history.inner.borrow_mut();
+            (inner.moved, inner.pending.take())
+        };
+        if !moved {
+            self.history.record(msg, (self.snapshot)(component));
+        }
+    }
+}
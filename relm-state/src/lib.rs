@@ -21,6 +21,10 @@
 
 //! This crate provide the non-GUI part of relm:
 //! Basic component and message connection methods.
+//!
+//! The futures of the components run on the glib main loop by default.
+//! Disable the `glib` feature to run them on a [`LocalExecutor`](struct.LocalExecutor.html)
+//! instead, without depending on glib.
 
 #![cfg_attr(feature = "use_impl_trait", feature(conservative_impl_trait))]
 #![warn(
@@ -34,24 +38,107 @@
 )]
 
 extern crate futures;
+#[cfg(feature = "glib")]
 extern crate futures_glib;
+#[cfg(feature = "glib")]
+extern crate glib_sys;
 #[macro_use]
 extern crate log;
 extern crate relm_core;
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub extern crate serde;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
 
+mod abort;
+mod bus;
+mod clock;
+mod cmd;
+mod executor;
+mod handle;
+mod history;
+mod inspector;
 mod into;
 mod macros;
+mod middleware;
+#[cfg(feature = "persist")]
+mod persist;
+mod pool;
+mod profiler;
+#[cfg(feature = "record")]
+mod record;
 mod stream;
+mod subscription;
+mod supervisor;
+mod timer;
+mod undo;
 
-use std::time::SystemTime;
+use std::any::type_name;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use futures::{Future, Stream};
+use futures::sync::oneshot;
 use futures::future::Executor as FutureExecutor;
-use futures_glib::{Executor, MainContext};
-pub use relm_core::EventStream;
+#[cfg(feature = "glib")]
+use futures_glib::MainContext;
+pub use relm_core::{
+    EventStream,
+    ObserverGuard,
+    ObserverHandle,
+    OverflowPolicy,
+    PausePolicy,
+    Priority,
+    Reply,
+    Response,
+    Sender,
+    WeakEventStream,
+};
 
+pub use abort::AbortHandle;
+#[cfg(feature = "glib")]
+pub use clock::GlibClock;
+pub use clock::{Clock, VirtualClock, clock, set_clock};
+pub use cmd::Cmd;
+#[cfg(feature = "glib")]
+pub use executor::GlibSpawner;
+pub use executor::{Executor, LocalExecutor, Spawner, local_executor};
+pub use handle::{Closed, ComponentHandle, ModelRef};
+pub use history::History;
+use history::HistoryInspector;
+use inspector::Inspector;
 pub use into::{IntoOption, IntoPair};
+pub use middleware::{GlobalMiddleware, Middleware, add_global_middleware};
+#[cfg(feature = "persist")]
+pub use persist::{Persist, PersistLocation, persist_path};
+#[cfg(feature = "persist")]
+use persist::PersistInspector;
+pub use pool::{PoolConfig, init_pool};
+pub use profiler::{
+    DEFAULT_MAX_TRACE_EVENTS,
+    HISTOGRAM_BOUNDS,
+    Profile,
+    SetterProfile,
+    SetterStats,
+    Stats,
+    UpdateStats,
+    is_profiling,
+    profile,
+    profile_setter,
+    reset_profile,
+    set_max_trace_events,
+    set_profiling,
+};
+#[cfg(feature = "record")]
+pub use record::{Record, Recorder, Replay};
 use stream::ToStream;
+pub use subscription::Subscriptions;
+pub use supervisor::{Panic, PanicPolicy, set_panic_hook};
+pub use timer::{debounce, throttle};
+pub use undo::{UndoKind, UndoStack, Undoable};
 
 macro_rules! relm_connect {
     ($_self:expr, $to_stream:expr, $success_callback:expr, $failure_callback:expr) => {{
@@ -60,7 +147,6 @@ macro_rules! relm_connect {
         let stream = $to_stream.to_stream();
         stream.map_err(move |error| {
             fail_event_stream.emit($failure_callback(error));
-            ()
         })
             .for_each(move |result| {
                 event_stream.emit($success_callback(result));
@@ -83,16 +169,32 @@ macro_rules! relm_connect_ignore {
     }};
 }
 
-/// Handle connection of futures to send messages to the [`update()`](trait.Update.html#tymethod.update) method.
+type CloseHooks<UPDATE> = RefCell<Vec<Box<Fn(&mut UPDATE)>>>;
+type Inspectors<UPDATE> = RefCell<Vec<Rc<Inspector<UPDATE>>>>;
+type Middlewares<MSG> = RefCell<Vec<Rc<Middleware<MSG>>>>;
+
+/// Handle connection of futures to send messages to the [`update()`](trait.Update.html#method.update) method.
 pub struct Relm<UPDATE: Update> {
+    close_hooks: Rc<CloseHooks<UPDATE>>,
     executor: Executor,
+    futures: Rc<RefCell<Vec<AbortHandle>>>,
+    inspectors: Rc<Inspectors<UPDATE>>,
+    middlewares: Rc<Middlewares<UPDATE::Msg>>,
+    #[cfg(feature = "persist")]
+    persisted: Rc<Cell<bool>>,
     stream: EventStream<UPDATE::Msg>,
 }
 
 impl<UPDATE: Update> Clone for Relm<UPDATE> {
     fn clone(&self) -> Self {
         Relm {
+            close_hooks: self.close_hooks.clone(),
             executor: self.executor.clone(),
+            futures: self.futures.clone(),
+            inspectors: self.inspectors.clone(),
+            middlewares: self.middlewares.clone(),
+            #[cfg(feature = "persist")]
+            persisted: self.persisted.clone(),
             stream: self.stream.clone(),
         }
     }
@@ -102,11 +204,46 @@ impl<UPDATE: Update> Relm<UPDATE> {
     /// Create a new relm stream handler.
     pub fn new(executor: Executor, stream: EventStream<UPDATE::Msg>) -> Self {
         Relm {
+            close_hooks: Rc::new(RefCell::new(vec![])),
             executor,
+            futures: Rc::new(RefCell::new(vec![])),
+            inspectors: Rc::new(RefCell::new(vec![])),
+            middlewares: Rc::new(RefCell::new(vec![])),
+            #[cfg(feature = "persist")]
+            persisted: Rc::new(Cell::new(false)),
             stream,
         }
     }
 
+    /// Add a middleware seeing the messages of this component before and after they are handled.
+    /// The middlewares are called in the order they were added before the message is handled and
+    /// in the reverse order after.
+    pub fn add_middleware<MIDDLEWARE>(&self, middleware: MIDDLEWARE)
+        where MIDDLEWARE: Middleware<UPDATE::Msg> + 'static,
+    {
+        self.middlewares.borrow_mut().push(Rc::new(middleware));
+    }
+
+    /// Call `callback` when the stream of this component is closed, right before the
+    /// [`on_close()`](trait.Update.html#method.on_close) method.
+    /// When the stream is closed during an update, the callback is called once the update returns.
+    pub fn before_close<CALLBACK>(&self, callback: CALLBACK)
+        where CALLBACK: Fn(&mut UPDATE) + 'static,
+    {
+        self.close_hooks.borrow_mut().push(Box::new(callback));
+    }
+
+    /// Start recording the state of this component before every undoable message, so that the
+    /// undo and redo messages restore it (see [`Undoable`](trait.Undoable.html)).
+    pub fn enable_undo(&self) -> UndoStack<UPDATE::Snapshot>
+        where UPDATE: Undoable + 'static,
+              UPDATE::Snapshot: 'static,
+    {
+        let stack = UndoStack::new(UPDATE::undo_depth());
+        self.inspectors.borrow_mut().push(Rc::new(stack.clone()));
+        stack
+    }
+
     #[cfg(feature = "use_impl_trait")]
     /// Connect a `Future` or a `Stream` called `to_stream` to send the message `success_callback`
     /// in case of success and `failure_callback` in case of failure.
@@ -169,8 +306,9 @@ impl<UPDATE: Update> Relm<UPDATE> {
     }
 
     /// Connect the future `to_stream` and spawn it on the tokio main loop.
+    /// The future is aborted when this component is destroyed or when the returned handle is used.
     pub fn connect_exec<CALLBACK, FAILCALLBACK, STREAM, TOSTREAM>(&self, to_stream: TOSTREAM, callback: CALLBACK,
-            failure_callback: FAILCALLBACK)
+            failure_callback: FAILCALLBACK) -> AbortHandle
         where CALLBACK: Fn(STREAM::Item) -> UPDATE::Msg + 'static,
               FAILCALLBACK: Fn(STREAM::Error) -> UPDATE::Msg + 'static,
               STREAM: Stream + 'static,
@@ -178,24 +316,135 @@ impl<UPDATE: Update> Relm<UPDATE> {
               UPDATE: 'static,
               UPDATE::Msg: 'static,
     {
-        self.exec(self.connect(to_stream, callback, failure_callback));
+        self.exec(self.connect(to_stream, callback, failure_callback))
     }
 
     /// Connect the future `to_stream` and spawn it on the tokio main loop, ignoring any error.
+    /// The future is aborted when this component is destroyed or when the returned handle is used.
     pub fn connect_exec_ignore_err<CALLBACK, STREAM, TOSTREAM>(&self, to_stream: TOSTREAM, callback: CALLBACK)
+            -> AbortHandle
         where CALLBACK: Fn(STREAM::Item) -> UPDATE::Msg + 'static,
               STREAM: Stream + 'static,
               TOSTREAM: ToStream<STREAM, Item=STREAM::Item, Error=STREAM::Error> + 'static,
               UPDATE: 'static,
               UPDATE::Msg: 'static,
     {
-        self.exec(self.connect_ignore_err(to_stream, callback));
+        self.exec(self.connect_ignore_err(to_stream, callback))
+    }
+
+    /// Get a stream whose messages are sent to this component once no other message was emitted
+    /// on it for `duration`.
+    /// This is useful for search-as-you-type: only the last message is handled by the
+    /// [`update()`](trait.Update.html#method.update) method.
+    pub fn debounce(&self, duration: Duration) -> EventStream<UPDATE::Msg>
+        where UPDATE: 'static,
+              UPDATE::Msg: 'static,
+    {
+        let (input, future) = timer::debouncer(self.timer_spawn(), &self.stream, duration);
+        let _ = self.exec(future);
+        input
+    }
+
+    /// Get a stream whose messages are sent to this component at most once every `duration`.
+    /// The first message is sent right away and the last message emitted during `duration` is
+    /// sent at the end of it.
+    pub fn throttle(&self, duration: Duration) -> EventStream<UPDATE::Msg>
+        where UPDATE: 'static,
+              UPDATE::Msg: 'static,
+    {
+        let (input, future) = timer::throttler(self.timer_spawn(), &self.stream, duration);
+        let _ = self.exec(future);
+        input
+    }
+
+    // NOTE: the timeouts of the debouncers and throttlers are spawned with exec() so that they are
+    // aborted when this component is destroyed.
+    fn timer_spawn(&self) -> timer::Spawn
+        where UPDATE: 'static,
+    {
+        let relm = self.clone();
+        Rc::new(move |future| {
+            let _ = relm.exec(future);
+        })
+    }
+
+    /// Publish `topic` on the application bus: every component subscribed to the `TOPIC` type
+    /// receives a message.
+    ///
+    /// The bus is local to the thread running the main loop.
+    pub fn publish<TOPIC: 'static>(&self, topic: TOPIC) {
+        bus::publish(&topic);
+    }
+
+    /// Subscribe to the `TOPIC`s published on the application bus.
+    /// Every time one is published, the message returned by `callback`, if any, is sent to this
+    /// component.
+    /// The subscription is removed when this component is destroyed.
+    ///
+    /// This is usually called in the [`subscriptions()`](trait.Update.html#method.subscriptions)
+    /// method.
+    pub fn subscribe<CALLBACK, RET, TOPIC>(&self, callback: CALLBACK)
+        where CALLBACK: Fn(&TOPIC) -> RET + 'static,
+              RET: IntoOption<UPDATE::Msg>,
+              TOPIC: 'static,
+              UPDATE::Msg: 'static,
+    {
+        bus::subscribe(&self.stream, callback);
     }
 
     /// Spawn a future in the tokio event loop.
-    pub fn exec<FUTURE: Future<Item=(), Error=()> + 'static>(&self, future: FUTURE) {
+    /// The future is aborted when this component is destroyed or when the returned handle is used.
+    pub fn exec<FUTURE: Future<Item=(), Error=()> + 'static>(&self, future: FUTURE) -> AbortHandle {
+        let (future, handle) = abort::abortable(future);
+        if self.stream.is_closed() {
+            handle.abort();
+        }
+        else {
+            let mut futures = self.futures.borrow_mut();
+            futures.retain(|future| !future.is_finished());
+            futures.push(handle.clone());
+        }
         // NOTE: no error can be returned from execute(), hence unwrap().
         self.executor.execute(future).unwrap();
+        handle
+    }
+
+    /// Send `msg` to this component at `instant`, as measured by the [`clock()`](fn.clock.html).
+    /// The message is not sent if this component is destroyed or if the returned handle is used
+    /// before.
+    pub fn emit_at(&self, instant: Instant, msg: UPDATE::Msg) -> AbortHandle
+        where UPDATE::Msg: 'static,
+    {
+        let stream = self.stream.clone();
+        let future = clock().delay_until(instant)
+            .map(move |()| stream.emit(msg));
+        self.exec(future)
+    }
+
+    /// Send `msg` to this component every `duration`, until it is destroyed or the returned
+    /// handle is used.
+    pub fn interval(&self, duration: Duration, msg: UPDATE::Msg) -> AbortHandle
+        where UPDATE::Msg: Clone + 'static,
+    {
+        let stream = self.stream.clone();
+        let future = clock().interval(duration)
+            .for_each(move |()| {
+                stream.emit(msg.clone());
+                Ok(())
+            });
+        self.exec(future)
+    }
+
+    /// Send `msg` to this component after `duration`.
+    /// The message is not sent if this component is destroyed or if the returned handle is used
+    /// before.
+    pub fn timeout(&self, duration: Duration, msg: UPDATE::Msg) -> AbortHandle
+        where UPDATE::Msg: 'static,
+    {
+        let stream = self.stream.clone();
+        let future = clock::timeout(duration)
+            .map(move |()| stream.emit(msg));
+        self.exec(future)
     }
 
     /// Get the handle of this stream.
@@ -203,6 +452,85 @@ impl<UPDATE: Update> Relm<UPDATE> {
         &self.executor
     }
 
+    /// Start recording the state of this component, as returned by `snapshot`, after every
+    /// message it handles.
+    /// The returned `History` keeps at most `capacity` states and is used to step backward and
+    /// forward in time.
+    ///
+    /// ## Note
+    /// Do not include the `History` itself in the snapshot.
+    pub fn time_travel<SNAPSHOT, FUNC>(&self, capacity: usize, snapshot: FUNC) -> History<UPDATE::Msg, SNAPSHOT>
+        where FUNC: Fn(&UPDATE) -> SNAPSHOT + 'static,
+              SNAPSHOT: Clone + 'static,
+              UPDATE: 'static,
+              UPDATE::Msg: Clone + 'static,
+    {
+        let history = History::new(capacity);
+        let inspector = HistoryInspector::new(history.clone(), snapshot);
+        self.inspectors.borrow_mut().push(Rc::new(inspector));
+        history
+    }
+
+    #[cfg(feature = "persist")]
+    /// Read the state saved by a previous run into `model` and save the state of this component
+    /// when it is closed (see [`Persist`](trait.Persist.html)).
+    /// Call this method in [`model()`](trait.Update.html#tymethod.model).
+    ///
+    /// Only the first call does something: when the model is created again, e.g. by
+    /// [`restart()`](trait.Update.html#method.restart), it is not overwritten by the saved state.
+    pub fn persist(&self, model: &mut UPDATE::Model)
+        where UPDATE: Persist + 'static,
+    {
+        if self.persisted.get() {
+            return;
+        }
+        self.persisted.set(true);
+        persist::load::<UPDATE>(model);
+        self.inspectors.borrow_mut().push(Rc::new(PersistInspector::new()));
+    }
+
+    /// Get a `Sender` to send messages to this component from another thread.
+    /// The messages are handled by the [`update()`](trait.Update.html#method.update) method, as usual.
+    pub fn sender(&self) -> Sender<UPDATE::Msg> {
+        self.stream.sender()
+    }
+
+    /// Run the blocking `function` on the worker pool, so that it does not freeze the GUI.
+    /// Its result is sent back to this component as the message `callback` in case of success
+    /// and `failure_callback` in case of failure.
+    /// The result is discarded if this component is destroyed or if the returned handle is used
+    /// before the end of `function`.
+    ///
+    /// The worker pool can be configured with [`init_pool()`](fn.init_pool.html).
+    pub fn spawn_blocking<CALLBACK, ERROR, FAILCALLBACK, FUNCTION, VALUE>(&self, function: FUNCTION,
+            callback: CALLBACK, failure_callback: FAILCALLBACK) -> AbortHandle
+        where CALLBACK: FnOnce(VALUE) -> UPDATE::Msg + 'static,
+              ERROR: Send + 'static,
+              FAILCALLBACK: FnOnce(ERROR) -> UPDATE::Msg + 'static,
+              FUNCTION: FnOnce() -> Result<VALUE, ERROR> + Send + 'static,
+              VALUE: Send + 'static,
+              UPDATE::Msg: 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        pool::spawn(move || {
+            // Do not start the job if its result is not needed anymore.
+            if !sender.is_canceled() {
+                let _ = sender.send(function());
+            }
+        });
+        let stream = self.stream.clone();
+        let future = receiver.then(move |result| {
+            match result {
+                Ok(Ok(value)) => stream.emit(callback(value)),
+                Ok(Err(error)) => stream.emit(failure_callback(error)),
+                // The function panicked, which was already reported by the worker.
+                Err(_) => (),
+            }
+            Ok(())
+        });
+        self.exec(future)
+    }
+
     /// Get the event stream of this stream.
     /// This is used internally by the library.
     pub fn stream(&self) -> &EventStream<UPDATE::Msg> {
@@ -221,7 +549,7 @@ pub trait Update
     type Model;
     /// The type of the parameter of the model() function used to initialize the model.
     type ModelParam: Sized;
-    /// The type of the messages sent to the [`update()`](trait.Update.html#tymethod.update) method.
+    /// The type of the messages sent to the [`update()`](trait.Update.html#method.update) method.
     type Msg;
 
     /// Create the initial model.
@@ -232,8 +560,73 @@ pub trait Update
     fn subscriptions(&mut self, _relm: &Relm<Self>) {
     }
 
+    /// Method called when the stream of the component is closed, i.e. when the component is
+    /// destroyed.
+    /// This is where the resources owned by the component, like files or child processes, can be
+    /// released.
+    fn on_close(&mut self) {
+    }
+
+    /// Declare the subscriptions depending on the current state of the component.
+    /// This method is called after the creation of the component and after every
+    /// [`update()`](trait.Update.html#method.update): the subscriptions whose key appeared are
+    /// started and the ones whose key disappeared are cancelled.
+    ///
+    /// By default, this method returns no subscriptions.
+    fn dynamic_subscriptions(&self) -> Subscriptions<Self::Msg> {
+        Subscriptions::new()
+    }
+
+    /// Get what happens when the [`update()`](trait.Update.html#method.update) method panics.
+    ///
+    /// By default, the panic is propagated.
+    fn panic_policy() -> PanicPolicy {
+        PanicPolicy::Abort
+    }
+
+    /// Get the message sent to the observers of this component when its
+    /// [`update()`](trait.Update.html#method.update) method panicked and its policy is not
+    /// `PanicPolicy::Abort`.
+    /// This is how the parent component can be notified, by connecting to this message.
+    /// The component itself does not receive this message.
+    ///
+    /// By default, no message is sent.
+    fn panic_msg(_panic: &Panic) -> Option<Self::Msg> {
+        None
+    }
+
+    /// Restore a consistent state after the [`update()`](trait.Update.html#method.update) method
+    /// panicked, when the policy is `PanicPolicy::Restart`.
+    /// This usually means creating a fresh model with the [`model()`](trait.Update.html#tymethod.model)
+    /// method.
+    ///
+    /// Since the parameter of `model()` is not available anymore, the model cannot be rebuilt by
+    /// default: the component is then frozen as with `PanicPolicy::Freeze`, so a component using
+    /// `PanicPolicy::Restart` must implement this method.
+    /// The `#[widget]` attribute generates it when the `model()` method has no parameter.
+    fn restart(&mut self, relm: &Relm<Self>) {
+        error!("The component does not implement restart(): freezing it instead");
+        let _ = relm.stream().close();
+    }
+
     /// Method called when a message is received from an event.
-    fn update(&mut self, event: Self::Msg);
+    ///
+    /// A component implements either this method or
+    /// [`update_with_commands()`](trait.Update.html#method.update_with_commands): since relm
+    /// only calls the latter, this method does nothing by default.
+    fn update(&mut self, _event: Self::Msg) {
+    }
+
+    /// Method called when a message is received from an event, returning the commands to execute.
+    /// Since the side effects are described by the returned `Cmd` instead of being executed in
+    /// this method, the update logic can be tested without a main loop.
+    ///
+    /// This is the only update method called by relm: by default, it calls
+    /// [`update()`](trait.Update.html#method.update) and returns `Cmd::None`.
+    fn update_with_commands(&mut self, event: Self::Msg) -> Cmd<Self::Msg> {
+        self.update(event);
+        Cmd::None
+    }
 }
 
 /// Trait for an `Update` object that can be created directly.
@@ -260,69 +653,1467 @@ impl DisplayVariant for () {
     }
 }
 
-/// Create an `Executor` attached to the main context.
+#[cfg(feature = "glib")]
+/// Create an `Executor` attached to the glib main context.
 pub fn create_executor() -> Executor {
     let cx = MainContext::default(|cx| cx.clone());
-    let executor = Executor::new();
+    let executor = futures_glib::Executor::new();
     executor.attach(&cx);
-    executor
+    Executor::new(executor)
+}
+
+#[cfg(feature = "glib")]
+/// Create an `Executor` running its futures from a glib source of the specified `priority` (see
+/// [`GlibSpawner`](struct.GlibSpawner.html)).
+pub fn create_executor_with_priority(priority: i32) -> Executor {
+    Executor::new(GlibSpawner::new(priority))
+}
+
+#[cfg(not(feature = "glib"))]
+/// Create an `Executor` running its futures on the [`local_executor()`](fn.local_executor.html)
+/// of the current thread.
+pub fn create_executor() -> Executor {
+    local_executor().executor()
 }
 
 /// Create a bare component, i.e. a component only implementing the Update trait, not the Widget
 /// trait.
-pub fn execute<UPDATE>(model_param: UPDATE::ModelParam) -> EventStream<UPDATE::Msg>
+pub fn execute<UPDATE>(model_param: UPDATE::ModelParam) -> ComponentHandle<UPDATE>
+where UPDATE: Update + UpdateNew + 'static
+{
+    execute_on::<UPDATE>(&create_executor(), model_param)
+}
+
+/// Create a bare component whose futures are run by `executor`.
+pub fn execute_on<UPDATE>(executor: &Executor, model_param: UPDATE::ModelParam) -> ComponentHandle<UPDATE>
 where UPDATE: Update + UpdateNew + 'static
 {
-    let executor = create_executor();
     let stream = EventStream::new();
 
     let relm = Relm::new(executor.clone(), stream.clone());
     let model = UPDATE::model(&relm, model_param);
-    let component = UPDATE::new(&relm, model);
+    let component = Rc::new(RefCell::new(UPDATE::new(&relm, model)));
 
-    init_component::<UPDATE>(&stream, component, &executor, &relm);
-    stream
+    init_shared_component::<UPDATE>(&stream, component.clone(), executor, &relm);
+    ComponentHandle::new(stream, component)
 }
 
 /// Initialize a component by creating its subscriptions and dispatching the messages from the
 /// stream.
-pub fn init_component<UPDATE>(stream: &EventStream<UPDATE::Msg>, mut component: UPDATE, executor: &Executor,
+pub fn init_component<UPDATE>(stream: &EventStream<UPDATE::Msg>, component: UPDATE, executor: &Executor,
     relm: &Relm<UPDATE>)
     where UPDATE: Update + 'static,
           UPDATE::Msg: DisplayVariant + 'static,
+{
+    init_shared_component(stream, Rc::new(RefCell::new(component)), executor, relm);
+}
+
+/// Initialize a component which is shared with other callbacks, like signal handlers.
+/// The component is borrowed mutably while a message is handled.
+pub fn init_shared_component<UPDATE>(stream: &EventStream<UPDATE::Msg>, component: Rc<RefCell<UPDATE>>,
+    executor: &Executor, relm: &Relm<UPDATE>)
+    where UPDATE: Update + 'static,
+          UPDATE::Msg: DisplayVariant + 'static,
 {
     let stream = stream.clone();
-    component.subscriptions(relm);
+    component.borrow_mut().subscriptions(relm);
+    let mut subscriptions = HashMap::new();
+    subscription::sync(&*component.borrow(), relm, &mut subscriptions);
+    let close_pending = Rc::new(Cell::new(false));
+    {
+        let close_pending = close_pending.clone();
+        let component = Rc::downgrade(&component);
+        let close_hooks = relm.close_hooks.clone();
+        let futures = relm.futures.clone();
+        let inspectors = relm.inspectors.clone();
+        stream.on_close(move || {
+            // The component is destroyed, so its futures are useless.
+            for future in futures.borrow_mut().drain(..) {
+                future.abort();
+            }
+            if let Some(component) = component.upgrade() {
+                match component.try_borrow_mut() {
+                    Ok(mut component) => close_component(&mut *component, &close_hooks, &inspectors),
+                    // The stream was closed by the update: call the hook once it is done.
+                    Err(_) => close_pending.set(true),
+                }
+            }
+        });
+    }
+    let relm = relm.clone();
     let event_future = stream.for_each(move |event| {
-        update_component(&mut component, event);
+        let mut component = component.borrow_mut();
+        supervisor::supervise(&mut *component, &relm, event, update_component);
+        if close_pending.get() {
+            close_pending.set(false);
+            close_component(&mut *component, &relm.close_hooks, &relm.inspectors);
+        }
+        else {
+            subscription::sync(&*component, &relm, &mut subscriptions);
+        }
         Ok(())
     });
     // NOTE: no error can be returned from execute(), hence unwrap().
     executor.execute(event_future).unwrap();
 }
 
-fn update_component<COMPONENT>(component: &mut COMPONENT, event: COMPONENT::Msg)
+fn close_component<COMPONENT: Update>(component: &mut COMPONENT, close_hooks: &CloseHooks<COMPONENT>,
+    inspectors: &Inspectors<COMPONENT>)
+{
+    // NOTE: take the hooks since they are only called once and they could add other hooks.
+    let hooks: Vec<_> = close_hooks.borrow_mut().drain(..).collect();
+    for hook in &hooks {
+        hook(component);
+    }
+    // NOTE: clone the inspectors since the component could add other inspectors.
+    let inspectors = inspectors.borrow().clone();
+    for inspector in &inspectors {
+        inspector.close(component);
+    }
+    component.on_close();
+}
+
+fn update_component<COMPONENT>(component: &mut COMPONENT, relm: &Relm<COMPONENT>, event: COMPONENT::Msg)
     where COMPONENT: Update,
+          COMPONENT::Msg: 'static,
 {
+    // NOTE: clone the middlewares since they might add other middlewares.
+    let middlewares = relm.middlewares.borrow().clone();
+    let event =
+        match middleware::before(&middlewares, event) {
+            Some(event) => event,
+            None => return,
+        };
+    let msg = event.display_variant();
+    let inspectors = relm.inspectors.borrow().clone();
+    for inspector in &inspectors {
+        inspector.before(component, &event);
+    }
+    let time = Instant::now();
+    let command = component.update_with_commands(event);
+    let duration = time.elapsed();
+    for inspector in &inspectors {
+        inspector.after(component);
+    }
+    if is_profiling() {
+        profiler::record_update(type_name::<COMPONENT>(), msg, time, duration, relm.stream.wait_time());
+    }
     if cfg!(debug_assertions) {
-        let time = SystemTime::now();
-        let debug = event.display_variant();
-        let debug =
-            if debug.len() > 100 {
-                format!("{}…", &debug[..100])
+        let ms = duration.subsec_nanos() as u64 / 1_000_000 + duration.as_secs() * 1000;
+        if ms >= 200 {
+            let debug =
+                if msg.len() > 100 {
+                    format!("{}…", &msg[..100])
+                }
+                else {
+                    msg.to_string()
+                };
+            warn!("The update function was slow to execute for message {}: {}ms", debug, ms);
+        }
+    }
+    middleware::after(&middlewares, msg, duration);
+    command.execute(relm);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::cell::{Cell, RefCell};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use futures::{Future, Stream, future, stream};
+    use futures::future::Executor;
+
+    use super::{
+        Cmd,
+        DisplayVariant,
+        History,
+        LocalExecutor,
+        ModelRef,
+        Panic,
+        PanicPolicy,
+        PoolConfig,
+        Relm,
+        Subscriptions,
+        UndoKind,
+        UndoStack,
+        Undoable,
+        Update,
+        UpdateNew,
+        VirtualClock,
+        execute_on,
+        set_clock,
+    };
+
+    use self::Msg::*;
+
+    #[derive(Clone)]
+    enum Msg {
+        Decrement,
+        Increment,
+        Quit,
+    }
+
+    impl DisplayVariant for Msg {
+        fn display_variant(&self) -> &'static str {
+            match *self {
+                Decrement => "Decrement",
+                Increment => "Increment",
+                Quit => "Quit",
+            }
+        }
+    }
+
+    struct Counter {
+        count: Rc<Cell<i32>>,
+        relm: Relm<Counter>,
+    }
+
+    impl Update for Counter {
+        type Model = Rc<Cell<i32>>;
+        type ModelParam = Rc<Cell<i32>>;
+        type Msg = Msg;
+
+        fn model(_: &Relm<Self>, count: Rc<Cell<i32>>) -> Rc<Cell<i32>> {
+            count
+        }
+
+        fn update(&mut self, msg: Msg) {
+            match msg {
+                Decrement => self.count.set(self.count.get() - 1),
+                Increment => self.count.set(self.count.get() + 1),
+                Quit => self.relm.stream().close().expect("close stream"),
+            }
+        }
+    }
+
+    impl UpdateNew for Counter {
+        fn new(relm: &Relm<Self>, count: Rc<Cell<i32>>) -> Self {
+            Counter {
+                count,
+                relm: relm.clone(),
+            }
+        }
+    }
+
+    impl ModelRef for Counter {
+        fn model_ref(&self) -> &Rc<Cell<i32>> {
+            &self.count
+        }
+    }
+
+    enum CommandMsg {
+        Add(i32),
+        Batch,
+        Delay,
+        Fetch(Result<i32, i32>),
+    }
+
+    impl DisplayVariant for CommandMsg {
+        fn display_variant(&self) -> &'static str {
+            match *self {
+                CommandMsg::Add(_) => "Add",
+                CommandMsg::Batch => "Batch",
+                CommandMsg::Delay => "Delay",
+                CommandMsg::Fetch(_) => "Fetch",
+            }
+        }
+    }
+
+    struct Commands {
+        count: Rc<Cell<i32>>,
+    }
+
+    impl Update for Commands {
+        type Model = Rc<Cell<i32>>;
+        type ModelParam = Rc<Cell<i32>>;
+        type Msg = CommandMsg;
+
+        fn model(_: &Relm<Self>, count: Rc<Cell<i32>>) -> Rc<Cell<i32>> {
+            count
+        }
+
+        fn update_with_commands(&mut self, msg: CommandMsg) -> Cmd<CommandMsg> {
+            match msg {
+                CommandMsg::Add(value) => {
+                    self.count.set(self.count.get() + value);
+                    Cmd::None
+                },
+                CommandMsg::Batch => Cmd::Batch(vec![Cmd::Msg(CommandMsg::Add(1)), Cmd::Msg(CommandMsg::Add(2))]),
+                CommandMsg::Delay => Cmd::Timeout(Duration::from_secs(1), CommandMsg::Add(100)),
+                CommandMsg::Fetch(result) =>
+                    Cmd::perform(future::result(result), CommandMsg::Add, |error| CommandMsg::Add(-error)),
+            }
+        }
+    }
+
+    impl UpdateNew for Commands {
+        fn new(_relm: &Relm<Self>, count: Rc<Cell<i32>>) -> Self {
+            Commands {
+                count,
+            }
+        }
+    }
+
+    #[test]
+    fn before_close() {
+        let executor = LocalExecutor::new();
+        let count = Rc::new(Cell::new(0));
+        let relm = Relm::<Counter>::new(executor.executor(), super::EventStream::new());
+        let component = Counter::new(&relm, count.clone());
+        let calls = Rc::new(Cell::new(0));
+        {
+            let calls = calls.clone();
+            relm.before_close(move |counter| {
+                calls.set(calls.get() + 1);
+                counter.count.set(-counter.count.get());
+            });
+        }
+        super::init_component(relm.stream(), component, &executor.executor(), &relm);
+        relm.stream().emit(Increment);
+        relm.stream().emit(Quit);
+        executor.run();
+        assert_eq!(calls.get(), 1);
+        assert_eq!(count.get(), -1);
+    }
+
+    #[test]
+    fn bus() {
+        struct Topic(i32);
+
+        let executor = LocalExecutor::new();
+        let count = Rc::new(Cell::new(0));
+        let relm = Relm::<Counter>::new(executor.executor(), super::EventStream::new());
+        let component = Counter::new(&relm, count.clone());
+        super::init_component(relm.stream(), component, &executor.executor(), &relm);
+        let captured = Rc::new(());
+        {
+            let captured = captured.clone();
+            relm.subscribe(move |topic: &Topic| {
+                let _ = &captured;
+                if topic.0 > 0 {
+                    Some(Increment)
+                }
+                else {
+                    None
+                }
+            });
+        }
+        relm.publish(Topic(1));
+        relm.publish(Topic(0));
+        relm.publish(Topic(2));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 2);
+
+        // The subscription is removed as soon as the stream is closed, without waiting for the next
+        // publication.
+        assert_eq!(Rc::strong_count(&captured), 2);
+        relm.stream().emit(Quit);
+        executor.run_until_stalled();
+        assert_eq!(Rc::strong_count(&captured), 1);
+        relm.publish(Topic(3));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 2);
+    }
+
+    #[cfg(feature = "glib")]
+    #[test]
+    fn glib_spawner() {
+        use std::ptr;
+
+        use glib_sys::{self, G_PRIORITY_HIGH_IDLE, G_PRIORITY_LOW};
+
+        use super::{GlibSpawner, Spawner};
+
+        fn iterate() {
+            unsafe {
+                let _ = glib_sys::g_main_context_iteration(ptr::null_mut(), glib_sys::GFALSE);
+            }
+        }
+
+        let low_spawner = GlibSpawner::new(G_PRIORITY_HIGH_IDLE);
+        low_spawner.set_priority(G_PRIORITY_LOW);
+        assert_eq!(low_spawner.priority(), G_PRIORITY_LOW);
+        let high_spawner = GlibSpawner::new(G_PRIORITY_HIGH_IDLE);
+        assert_eq!(high_spawner.priority(), G_PRIORITY_HIGH_IDLE);
+
+        let order = Rc::new(RefCell::new(vec![]));
+        for &(spawner, name) in &[(&low_spawner, "low"), (&high_spawner, "high")] {
+            let order = order.clone();
+            spawner.spawn(Box::new(future::lazy(move || {
+                order.borrow_mut().push(name);
+                Ok(())
+            })));
+        }
+        // NOTE: an iteration only dispatches the ready sources with the highest priority.
+        iterate();
+        assert_eq!(*order.borrow(), vec!["high"]);
+        for _ in 0..10 {
+            if order.borrow().len() == 2 {
+                break;
+            }
+            iterate();
+        }
+        assert_eq!(*order.borrow(), vec!["high", "low"]);
+    }
+
+    #[cfg(feature = "record")]
+    #[test]
+    fn record_replay() {
+        use std::{env, fs, process};
+
+        use super::{EventStream, Recorder, Replay};
+
+        let file = env::temp_dir().join(format!("relm-record-{}.json", process::id()));
+        let recorder = Recorder::new(&file).expect("create recorder");
+        let stream = EventStream::new();
+        let other_stream = EventStream::new();
+        let _handle = recorder.record("counter", &stream);
+        let _other_handle = recorder.record("other", &other_stream);
+        stream.emit(1);
+        other_stream.emit(10);
+        stream.emit(2);
+        // NOTE: the recorder is neither flushed nor dropped: the messages must be written right away.
+        let replay = Replay::<i32>::load(&file, "counter").expect("load replay");
+        let _ = fs::remove_file(&file);
+        let messages: Vec<_> = replay.records().iter().map(|record| record.msg).collect();
+        assert_eq!(messages, vec![1, 2]);
+        assert!(replay.records().iter().all(|record| record.path == "counter"));
+        assert!(replay.records()[0].elapsed <= replay.records()[1].elapsed);
+    }
+
+    #[cfg(feature = "persist")]
+    #[test]
+    fn persist() {
+        use std::{env, fs, process};
+
+        use super::{Persist, persist_path};
+
+        struct Saver {
+            count: Rc<Cell<i32>>,
+            relm: Relm<Saver>,
+        }
+
+        impl Update for Saver {
+            type Model = Rc<Cell<i32>>;
+            type ModelParam = ();
+            type Msg = Msg;
+
+            fn model(relm: &Relm<Self>, _: ()) -> Rc<Cell<i32>> {
+                let mut count = Rc::new(Cell::new(0));
+                relm.persist(&mut count);
+                count
+            }
+
+            fn update(&mut self, msg: Msg) {
+                match msg {
+                    // Persisting again must not overwrite the model with the saved state.
+                    Decrement => {
+                        let mut count = self.count.clone();
+                        self.relm.persist(&mut count);
+                    },
+                    Increment => self.count.set(self.count.get() + 1),
+                    Quit => (),
+                }
+            }
+        }
+
+        impl UpdateNew for Saver {
+            fn new(relm: &Relm<Self>, count: Rc<Cell<i32>>) -> Self {
+                Saver {
+                    count,
+                    relm: relm.clone(),
+                }
+            }
+        }
+
+        impl ModelRef for Saver {
+            fn model_ref(&self) -> &Rc<Cell<i32>> {
+                &self.count
+            }
+        }
+
+        impl Persist for Saver {
+            type State = i32;
+
+            fn load_state(count: &mut Rc<Cell<i32>>, state: i32) {
+                count.set(state);
+            }
+
+            fn persist_name() -> String {
+                "saver".to_string()
+            }
+
+            fn save_state(&self) -> i32 {
+                self.count.get()
+            }
+        }
+
+        let dir = env::temp_dir().join(format!("relm-persist-{}", process::id()));
+        env::set_var("XDG_STATE_HOME", &dir);
+        let executor = LocalExecutor::new();
+
+        let component = execute_on::<Saver>(&executor.executor(), ());
+        component.emit(Increment);
+        component.emit(Increment);
+        executor.run_until_stalled();
+        assert!(!persist_path::<Saver>().expect("persist path").exists());
+        component.close();
+        executor.run_until_stalled();
+        assert!(persist_path::<Saver>().expect("persist path").starts_with(&dir));
+        assert!(persist_path::<Saver>().expect("persist path").exists());
+
+        let component = execute_on::<Saver>(&executor.executor(), ());
+        assert_eq!(component.with_model(|count| count.get()), 2);
+        component.emit(Increment);
+        component.emit(Decrement);
+        executor.run_until_stalled();
+        assert_eq!(component.with_model(|count| count.get()), 3);
+        component.close();
+        executor.run_until_stalled();
+
+        let component = execute_on::<Saver>(&executor.executor(), ());
+        let count = component.with_model(|count| count.get());
+        component.close();
+        executor.run_until_stalled();
+        let _ = fs::remove_dir_all(&dir);
+        // The state of the second component was saved when it was closed.
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn virtual_clock() {
+        use super::Clock;
+
+        let (executor, clock, relm, count) = virtual_counter();
+        let _ = relm.timeout(Duration::from_secs(1), Increment);
+        let _ = relm.emit_at(clock.now() + Duration::from_millis(2500), Decrement);
+        let _ = relm.interval(Duration::from_secs(2), Increment);
+        let aborted = relm.timeout(Duration::from_millis(1500), Decrement);
+        executor.run_until_stalled();
+        aborted.abort();
+        clock.advance(Duration::from_millis(999));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 0);
+        clock.advance(Duration::from_millis(1));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+        clock.advance(Duration::from_secs(1));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 2);
+        clock.advance(Duration::from_millis(500));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+        clock.advance(Duration::from_millis(1500));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 2);
+
+        // The timers are aborted when the component is closed.
+        relm.stream().emit(Quit);
+        executor.run_until_stalled();
+        assert!(executor.is_empty());
+        clock.advance(Duration::from_secs(10));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 2);
+    }
+
+    fn virtual_counter() -> (LocalExecutor, VirtualClock, Relm<Counter>, Rc<Cell<i32>>) {
+        let executor = LocalExecutor::new();
+        let clock = VirtualClock::new();
+        set_clock(clock.clone());
+        let count = Rc::new(Cell::new(0));
+        let relm = Relm::<Counter>::new(executor.executor(), super::EventStream::new());
+        let component = Counter::new(&relm, count.clone());
+        super::init_component(relm.stream(), component, &executor.executor(), &relm);
+        (executor, clock, relm, count)
+    }
+
+    #[test]
+    fn debounce() {
+        let (executor, clock, relm, count) = virtual_counter();
+        let input = relm.debounce(Duration::from_millis(100));
+        input.emit(Decrement);
+        executor.run_until_stalled();
+        clock.advance(Duration::from_millis(50));
+        input.emit(Increment);
+        executor.run_until_stalled();
+        clock.advance(Duration::from_millis(50));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 0);
+        clock.advance(Duration::from_millis(50));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+        clock.advance(Duration::from_millis(100));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+
+        // The pending timeout is aborted when the component is closed.
+        input.emit(Increment);
+        executor.run_until_stalled();
+        relm.stream().emit(Quit);
+        executor.run_until_stalled();
+        assert!(executor.is_empty());
+        clock.advance(Duration::from_millis(100));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn debounce_aborts_previous_timeouts() {
+        use super::{AbortHandle, abort, timer};
+        use super::executor::Spawner;
+
+        let (executor, clock, relm, count) = virtual_counter();
+        let timeouts: Rc<RefCell<Vec<AbortHandle>>> = Rc::new(RefCell::new(vec![]));
+        let spawn: timer::Spawn = {
+            let executor = executor.clone();
+            let timeouts = timeouts.clone();
+            Rc::new(move |future| {
+                let (future, handle) = abort::abortable(future);
+                timeouts.borrow_mut().push(handle);
+                executor.spawn(Box::new(future));
+            })
+        };
+        let (input, future) = timer::debouncer(spawn, relm.stream(), Duration::from_millis(100));
+        executor.spawn(future);
+        for _ in 0..3 {
+            input.emit(Increment);
+            executor.run_until_stalled();
+            clock.advance(Duration::from_millis(10));
+        }
+        executor.run_until_stalled();
+        let running = timeouts.borrow().iter()
+            .filter(|handle| !handle.is_finished())
+            .count();
+        assert_eq!(running, 1);
+        clock.advance(Duration::from_millis(100));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+        assert!(timeouts.borrow().iter().all(AbortHandle::is_finished));
+    }
+
+    #[test]
+    fn throttle() {
+        let (executor, clock, relm, count) = virtual_counter();
+        let input = relm.throttle(Duration::from_millis(100));
+        input.emit(Increment);
+        input.emit(Increment);
+        input.emit(Decrement);
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+        clock.advance(Duration::from_millis(100));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 0);
+        clock.advance(Duration::from_millis(100));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 0);
+        input.emit(Increment);
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+
+        // The window is aborted when the component is closed.
+        input.emit(Increment);
+        executor.run_until_stalled();
+        relm.stream().emit(Quit);
+        executor.run_until_stalled();
+        assert!(executor.is_empty());
+        clock.advance(Duration::from_millis(100));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+    }
+
+    struct Subscriber {
+        count: Rc<Cell<i32>>,
+    }
+
+    impl Update for Subscriber {
+        type Model = Rc<Cell<i32>>;
+        type ModelParam = Rc<Cell<i32>>;
+        type Msg = Msg;
+
+        fn model(_: &Relm<Self>, count: Rc<Cell<i32>>) -> Rc<Cell<i32>> {
+            count
+        }
+
+        fn dynamic_subscriptions(&self) -> Subscriptions<Msg> {
+            let subscriptions = Subscriptions::new()
+                .interval("interval", Duration::from_secs(1), Decrement);
+            if self.count.get() >= 0 && self.count.get() < 3 {
+                subscriptions.restarting_stream("once", || stream::once::<Msg, ()>(Ok(Increment)))
+            }
+            else {
+                subscriptions
+            }
+        }
+
+        fn update(&mut self, msg: Msg) {
+            match msg {
+                Decrement => self.count.set(self.count.get() - 10),
+                Increment => self.count.set(self.count.get() + 1),
+                Quit => (),
+            }
+        }
+    }
+
+    impl UpdateNew for Subscriber {
+        fn new(_relm: &Relm<Self>, count: Rc<Cell<i32>>) -> Self {
+            Subscriber {
+                count,
+            }
+        }
+    }
+
+    #[test]
+    fn subscriptions() {
+        let executor = LocalExecutor::new();
+        let clock = VirtualClock::new();
+        set_clock(clock.clone());
+        let count = Rc::new(Cell::new(0));
+        let component = execute_on::<Subscriber>(&executor.executor(), count.clone());
+        executor.run_until_stalled();
+        // The stream subscription is started again after it ended, until its key is removed.
+        assert_eq!(count.get(), 3);
+        // The interval subscription is not restarted by the updates.
+        clock.advance(Duration::from_millis(500));
+        component.emit(Increment);
+        executor.run_until_stalled();
+        clock.advance(Duration::from_millis(500));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), -6);
+        component.close();
+        executor.run_until_stalled();
+        assert!(executor.is_empty());
+    }
+
+    struct Fetcher {
+        count: Rc<Cell<i32>>,
+    }
+
+    impl Update for Fetcher {
+        type Model = Rc<Cell<i32>>;
+        type ModelParam = Rc<Cell<i32>>;
+        type Msg = Msg;
+
+        fn model(_: &Relm<Self>, count: Rc<Cell<i32>>) -> Rc<Cell<i32>> {
+            count
+        }
+
+        fn dynamic_subscriptions(&self) -> Subscriptions<Msg> {
+            // NOTE: the subscription is bounded so that the test does not hang if it restarts.
+            if self.count.get() < 5 {
+                Subscriptions::new()
+                    .stream("fetch", || stream::once::<Msg, ()>(Ok(Increment)))
             }
             else {
-                debug.to_string()
-            };
-        component.update(event);
-        if let Ok(duration) = time.elapsed() {
-            let ms = duration.subsec_nanos() as u64 / 1_000_000 + duration.as_secs() * 1000;
-            if ms >= 200 {
-                warn!("The update function was slow to execute for message {}: {}ms", debug, ms);
+                Subscriptions::new()
+            }
+        }
+
+        fn update(&mut self, msg: Msg) {
+            match msg {
+                Decrement => self.count.set(self.count.get() - 1),
+                Increment => self.count.set(self.count.get() + 1),
+                Quit => (),
+            }
+        }
+    }
+
+    impl UpdateNew for Fetcher {
+        fn new(_relm: &Relm<Self>, count: Rc<Cell<i32>>) -> Self {
+            Fetcher {
+                count,
+            }
+        }
+    }
+
+    #[test]
+    fn finished_subscriptions() {
+        let executor = LocalExecutor::new();
+        let count = Rc::new(Cell::new(0));
+        let component = execute_on::<Fetcher>(&executor.executor(), count.clone());
+        executor.run_until_stalled();
+        // The message of the ended stream triggers an update, which does not restart the stream.
+        assert_eq!(count.get(), 1);
+        component.emit(Quit);
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+        component.close();
+        executor.run_until_stalled();
+        assert!(executor.is_empty());
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum TravelMsg {
+        Add(i32),
+        Back,
+        Forward,
+        Goto(usize),
+    }
+
+    impl DisplayVariant for TravelMsg {
+        fn display_variant(&self) -> &'static str {
+            match *self {
+                TravelMsg::Add(_) => "Add",
+                TravelMsg::Back => "Back",
+                TravelMsg::Forward => "Forward",
+                TravelMsg::Goto(_) => "Goto",
+            }
+        }
+    }
+
+    struct TravelModel {
+        history: History<TravelMsg, i32>,
+        value: i32,
+    }
+
+    struct Traveler {
+        model: TravelModel,
+    }
+
+    impl Update for Traveler {
+        type Model = TravelModel;
+        type ModelParam = ();
+        type Msg = TravelMsg;
+
+        fn model(relm: &Relm<Self>, _: ()) -> TravelModel {
+            TravelModel {
+                history: relm.time_travel(3, |traveler: &Traveler| traveler.model.value),
+                value: 0,
+            }
+        }
+
+        fn update(&mut self, msg: TravelMsg) {
+            let snapshot =
+                match msg {
+                    TravelMsg::Add(value) => {
+                        self.model.value += value;
+                        None
+                    },
+                    TravelMsg::Back => self.model.history.back(),
+                    TravelMsg::Forward => self.model.history.forward(),
+                    TravelMsg::Goto(index) => self.model.history.goto(index),
+                };
+            if let Some(value) = snapshot {
+                self.model.value = value;
+            }
+        }
+    }
+
+    impl UpdateNew for Traveler {
+        fn new(_relm: &Relm<Self>, model: TravelModel) -> Self {
+            Traveler {
+                model,
+            }
+        }
+    }
+
+    impl ModelRef for Traveler {
+        fn model_ref(&self) -> &TravelModel {
+            &self.model
+        }
+    }
+
+    #[test]
+    fn history() {
+        let executor = LocalExecutor::new();
+        let component = execute_on::<Traveler>(&executor.executor(), ());
+        let state = || component.with_model(|model|
+            (model.value, model.history.position(), model.history.len(), model.history.is_traveling()));
+        component.emit(TravelMsg::Add(1));
+        executor.run_until_stalled();
+        component.with_model(|model| {
+            assert_eq!(model.history.snapshot(0), Some(0));
+            assert_eq!(model.history.message(0), None);
+            assert_eq!(model.history.message(1), Some(TravelMsg::Add(1)));
+        });
+        component.emit(TravelMsg::Add(2));
+        component.emit(TravelMsg::Add(3));
+        executor.run_until_stalled();
+        // The history keeps at most 3 states.
+        assert_eq!(state(), (6, 2, 3, false));
+        component.with_model(|model| assert_eq!(model.history.snapshot(0), Some(1)));
+
+        // The messages used to travel are not recorded.
+        component.emit(TravelMsg::Back);
+        executor.run_until_stalled();
+        assert_eq!(state(), (3, 1, 3, true));
+        component.emit(TravelMsg::Back);
+        component.emit(TravelMsg::Back);
+        executor.run_until_stalled();
+        assert_eq!(state(), (1, 0, 3, true));
+        component.emit(TravelMsg::Forward);
+        executor.run_until_stalled();
+        assert_eq!(state(), (3, 1, 3, true));
+        component.emit(TravelMsg::Goto(5));
+        executor.run_until_stalled();
+        assert_eq!(state(), (3, 1, 3, true));
+
+        // Handling a message in the past discards the following states.
+        component.emit(TravelMsg::Add(10));
+        executor.run_until_stalled();
+        assert_eq!(state(), (13, 2, 3, false));
+        component.with_model(|model| assert_eq!(model.history.message(2), Some(TravelMsg::Add(10))));
+        component.emit(TravelMsg::Goto(0));
+        executor.run_until_stalled();
+        assert_eq!(state(), (1, 0, 3, true));
+        component.with_model(|model| model.history.clear());
+        assert_eq!(state(), (1, 0, 0, false));
+    }
+
+    enum EditMsg {
+        Clear,
+        Redo,
+        Save,
+        Type(char),
+        Undo,
+    }
+
+    impl DisplayVariant for EditMsg {
+        fn display_variant(&self) -> &'static str {
+            match *self {
+                EditMsg::Clear => "Clear",
+                EditMsg::Redo => "Redo",
+                EditMsg::Save => "Save",
+                EditMsg::Type(_) => "Type",
+                EditMsg::Undo => "Undo",
+            }
+        }
+    }
+
+    struct EditModel {
+        saved: String,
+        stack: UndoStack<String>,
+        text: String,
+    }
+
+    struct Editor {
+        model: EditModel,
+    }
+
+    impl Update for Editor {
+        type Model = EditModel;
+        type ModelParam = ();
+        type Msg = EditMsg;
+
+        fn model(relm: &Relm<Self>, _: ()) -> EditModel {
+            EditModel {
+                saved: String::new(),
+                stack: relm.enable_undo(),
+                text: String::new(),
+            }
+        }
+
+        fn update(&mut self, msg: EditMsg) {
+            match msg {
+                EditMsg::Clear => self.model.text.clear(),
+                EditMsg::Save => self.model.saved = self.model.text.clone(),
+                EditMsg::Type(character) => self.model.text.push(character),
+                EditMsg::Redo | EditMsg::Undo => (),
             }
         }
     }
-    else {
-        component.update(event)
+
+    impl Undoable for Editor {
+        type Snapshot = String;
+
+        fn undo_depth() -> usize {
+            2
+        }
+
+        fn undo_kind(msg: &EditMsg) -> UndoKind {
+            match *msg {
+                EditMsg::Clear => UndoKind::Record,
+                EditMsg::Redo => UndoKind::Redo,
+                EditMsg::Save => UndoKind::Ignore,
+                EditMsg::Type(_) => UndoKind::Group("typing"),
+                EditMsg::Undo => UndoKind::Undo,
+            }
+        }
+
+        fn restore(&mut self, snapshot: String) {
+            self.model.text = snapshot;
+        }
+
+        fn snapshot(&self) -> String {
+            self.model.text.clone()
+        }
+    }
+
+    impl UpdateNew for Editor {
+        fn new(_relm: &Relm<Self>, model: EditModel) -> Self {
+            Editor {
+                model,
+            }
+        }
+    }
+
+    impl ModelRef for Editor {
+        fn model_ref(&self) -> &EditModel {
+            &self.model
+        }
+    }
+
+    #[test]
+    fn undo() {
+        let executor = LocalExecutor::new();
+        let component = execute_on::<Editor>(&executor.executor(), ());
+        let send = |msgs: Vec<EditMsg>| {
+            for msg in msgs {
+                component.emit(msg);
+            }
+            executor.run_until_stalled();
+            component.with_model(|model| (model.text.clone(), model.stack.can_undo(), model.stack.can_redo()))
+        };
+        // The consecutive messages of a group are undone together and the ignored messages are not
+        // undoable.
+        assert_eq!(send(vec![EditMsg::Type('a'), EditMsg::Type('b'), EditMsg::Save]), ("ab".to_string(), true, false));
+        assert_eq!(send(vec![EditMsg::Undo]), (String::new(), false, true));
+        assert_eq!(send(vec![EditMsg::Redo]), ("ab".to_string(), true, false));
+        component.with_model(|model| assert_eq!(model.saved, "ab"));
+
+        // Only the last 2 steps are kept.
+        assert_eq!(send(vec![EditMsg::Clear, EditMsg::Type('c')]), ("c".to_string(), true, false));
+        assert_eq!(send(vec![EditMsg::Undo]), (String::new(), true, true));
+        assert_eq!(send(vec![EditMsg::Undo]), ("ab".to_string(), false, true));
+        assert_eq!(send(vec![EditMsg::Undo]), ("ab".to_string(), false, true));
+        assert_eq!(send(vec![EditMsg::Redo]), (String::new(), true, true));
+
+        // A new step discards the steps which could be redone.
+        assert_eq!(send(vec![EditMsg::Type('d')]), ("d".to_string(), true, false));
+        assert_eq!(send(vec![EditMsg::Redo]), ("d".to_string(), true, false));
+        component.with_model(|model| {
+            model.stack.end_group();
+        });
+        assert_eq!(send(vec![EditMsg::Type('e'), EditMsg::Undo]), ("d".to_string(), true, true));
+        component.with_model(|model| model.stack.clear());
+        assert_eq!(send(vec![EditMsg::Undo]), ("d".to_string(), false, false));
+    }
+
+    #[test]
+    fn middlewares() {
+        use super::{GlobalMiddleware, Middleware, add_global_middleware};
+
+        struct Logger {
+            log: Rc<RefCell<Vec<String>>>,
+            name: &'static str,
+        }
+
+        impl Middleware<Msg> for Logger {
+            fn before(&self, msg: Msg) -> Option<Msg> {
+                self.log.borrow_mut().push(format!("{} before {}", self.name, msg.display_variant()));
+                Some(msg)
+            }
+
+            fn after(&self, msg: &'static str, _duration: Duration) {
+                self.log.borrow_mut().push(format!("{} after {}", self.name, msg));
+            }
+        }
+
+        struct Global {
+            log: Rc<RefCell<Vec<String>>>,
+        }
+
+        impl GlobalMiddleware for Global {
+            fn before(&self, msg: Box<Any>, variant: &'static str) -> Option<Box<Any>> {
+                self.log.borrow_mut().push(format!("global before {}", variant));
+                match msg.downcast_ref::<Msg>() {
+                    Some(&Decrement) => Some(Box::new(Increment)),
+                    Some(&Quit) => None,
+                    _ => Some(msg),
+                }
+            }
+
+            fn after(&self, msg: &'static str, _duration: Duration) {
+                self.log.borrow_mut().push(format!("global after {}", msg));
+            }
+        }
+
+        let log = Rc::new(RefCell::new(vec![]));
+        add_global_middleware(Global { log: log.clone() });
+        let executor = LocalExecutor::new();
+        let count = Rc::new(Cell::new(0));
+        let relm = Relm::<Counter>::new(executor.executor(), super::EventStream::new());
+        relm.add_middleware(Logger { log: log.clone(), name: "first" });
+        relm.add_middleware(Logger { log: log.clone(), name: "second" });
+        let component = Counter::new(&relm, count.clone());
+        super::init_component(relm.stream(), component, &executor.executor(), &relm);
+        relm.stream().emit(Decrement);
+        relm.stream().emit(Quit);
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+        assert!(!relm.stream().is_closed());
+        assert_eq!(*log.borrow(), vec![
+            "global before Decrement",
+            "first before Increment",
+            "second before Increment",
+            "second after Increment",
+            "first after Increment",
+            "global after Increment",
+            "global before Quit",
+        ]);
+    }
+
+    #[test]
+    fn profiler() {
+        use std::time::Instant;
+
+        use super::{HISTOGRAM_BOUNDS, profile, reset_profile, set_max_trace_events};
+        use super::profiler::record_update;
+
+        reset_profile();
+        let start = Instant::now();
+        record_update("Counter", "Increment", start, Duration::from_millis(2), Some(Duration::from_secs(2)));
+        record_update("Counter", "Increment", start, Duration::new(0, 5_000), None);
+        record_update("Counter", "Increment", start, Duration::new(0, 10_000), None);
+        record_update("Quote\"\\", "New\nline", start, Duration::from_secs(0), None);
+        let recorded = profile();
+        let updates = recorded.updates();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].msg, "Increment");
+        assert_eq!(updates[0].latency.count, 3);
+        assert_eq!(updates[0].latency.histogram, [1, 1, 0, 1, 0, 0, 0]);
+        assert_eq!(updates[0].latency.max, Duration::from_millis(2));
+        assert_eq!(updates[0].queue_wait.count, 1);
+        assert_eq!(updates[0].queue_wait.histogram[HISTOGRAM_BOUNDS.len()], 1);
+        assert_eq!(recorded.to_json(),
+            "{\"updates\":[{\"component\":\"Counter\",\"msg\":\"Increment\",\"latency\":{\"count\":3,\
+            \"histogram\":[1,1,0,1,0,0,0],\"max_us\":2000,\"total_us\":2015},\"queue_wait\":{\"count\":1,\
+            \"histogram\":[0,0,0,0,0,0,1],\"max_us\":2000000,\"total_us\":2000000}},{\"component\":\"Quote\\\"\\\\\",\
+            \"msg\":\"New\\nline\",\"latency\":{\"count\":1,\"histogram\":[1,0,0,0,0,0,0],\"max_us\":0,\"total_us\":0},\
+            \"queue_wait\":{\"count\":0,\"histogram\":[0,0,0,0,0,0,0],\"max_us\":0,\"total_us\":0}}],\"setters\":[]}");
+        let trace = recorded.to_chrome_trace();
+        assert!(trace.starts_with("{\"traceEvents\":[{\"name\":\"Increment\",\"cat\":\"update\",\"ph\":\"X\","));
+        assert!(trace.ends_with(",\"dur\":0,\"pid\":1,\"tid\":1,\"args\":{\"component\":\"Quote\\\"\\\\\"}}]}"));
+        assert_eq!(trace.matches("\"ph\":\"X\"").count(), 4);
+
+        reset_profile();
+        set_max_trace_events(2);
+        for _ in 0..3 {
+            record_update("Counter", "Increment", start, Duration::from_secs(0), None);
+        }
+        record_update("Counter", "Quit", start, Duration::from_secs(0), None);
+        let trace = profile().to_chrome_trace();
+        assert_eq!(trace.matches("\"name\":\"Increment\"").count(), 1);
+        assert_eq!(trace.matches("\"name\":\"Quit\"").count(), 1);
+        assert_eq!(profile().updates()[0].latency.count, 3);
+    }
+
+    thread_local! {
+        static POLICY: Cell<PanicPolicy> = const { Cell::new(PanicPolicy::Abort) };
+    }
+
+    enum CrashMsg {
+        Crash,
+        Crashed,
+        Ping,
+    }
+
+    impl DisplayVariant for CrashMsg {
+        fn display_variant(&self) -> &'static str {
+            match *self {
+                CrashMsg::Crash => "Crash",
+                CrashMsg::Crashed => "Crashed",
+                CrashMsg::Ping => "Ping",
+            }
+        }
+    }
+
+    struct Crashing {
+        log: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl Update for Crashing {
+        type Model = Rc<RefCell<Vec<&'static str>>>;
+        type ModelParam = Rc<RefCell<Vec<&'static str>>>;
+        type Msg = CrashMsg;
+
+        fn model(_: &Relm<Self>, log: Rc<RefCell<Vec<&'static str>>>) -> Rc<RefCell<Vec<&'static str>>> {
+            log
+        }
+
+        fn on_close(&mut self) {
+            self.log.borrow_mut().push("close");
+        }
+
+        fn panic_msg(_panic: &Panic) -> Option<CrashMsg> {
+            Some(CrashMsg::Crashed)
+        }
+
+        fn panic_policy() -> PanicPolicy {
+            POLICY.with(Cell::get)
+        }
+
+        fn restart(&mut self, _relm: &Relm<Self>) {
+            self.log.borrow_mut().push("restart");
+        }
+
+        fn update(&mut self, msg: CrashMsg) {
+            match msg {
+                CrashMsg::Crash => panic!("crash"),
+                CrashMsg::Crashed => self.log.borrow_mut().push("crashed"),
+                CrashMsg::Ping => self.log.borrow_mut().push("ping"),
+            }
+        }
+    }
+
+    impl UpdateNew for Crashing {
+        fn new(_relm: &Relm<Self>, log: Rc<RefCell<Vec<&'static str>>>) -> Self {
+            Crashing {
+                log,
+            }
+        }
+    }
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+    type RunResult = Result<(), Box<Any + Send>>;
+
+    fn crash(policy: PanicPolicy) -> (Log, Rc<Cell<i32>>, RunResult) {
+        POLICY.with(|current_policy| current_policy.set(policy));
+        let executor = LocalExecutor::new();
+        let log = Rc::new(RefCell::new(vec![]));
+        let component = execute_on::<Crashing>(&executor.executor(), log.clone());
+        let crashed = Rc::new(Cell::new(0));
+        {
+            let crashed = crashed.clone();
+            let _ = component.stream().observe(move |msg| {
+                if let CrashMsg::Crashed = *msg {
+                    crashed.set(crashed.get() + 1);
+                }
+            });
+        }
+        component.emit(CrashMsg::Crash);
+        component.emit(CrashMsg::Ping);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| executor.run_until_stalled()));
+        (log, crashed, result)
+    }
+
+    #[test]
+    fn supervisor_abort() {
+        let (log, crashed, result) = crash(PanicPolicy::Abort);
+        assert!(result.is_err());
+        assert_eq!(crashed.get(), 0);
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn supervisor_freeze() {
+        let (log, crashed, result) = crash(PanicPolicy::Freeze);
+        assert!(result.is_ok());
+        assert_eq!(crashed.get(), 1);
+        assert_eq!(*log.borrow(), vec!["close"]);
+    }
+
+    #[test]
+    fn supervisor_restart() {
+        let (log, crashed, result) = crash(PanicPolicy::Restart);
+        assert!(result.is_ok());
+        assert_eq!(crashed.get(), 1);
+        assert_eq!(*log.borrow(), vec!["restart", "ping"]);
+    }
+
+    struct Unrestartable {
+        log: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl Update for Unrestartable {
+        type Model = Rc<RefCell<Vec<&'static str>>>;
+        type ModelParam = Rc<RefCell<Vec<&'static str>>>;
+        type Msg = CrashMsg;
+
+        fn model(_: &Relm<Self>, log: Rc<RefCell<Vec<&'static str>>>) -> Rc<RefCell<Vec<&'static str>>> {
+            log
+        }
+
+        fn on_close(&mut self) {
+            self.log.borrow_mut().push("close");
+        }
+
+        fn panic_policy() -> PanicPolicy {
+            PanicPolicy::Restart
+        }
+
+        fn update(&mut self, msg: CrashMsg) {
+            match msg {
+                CrashMsg::Crash => panic!("crash"),
+                CrashMsg::Crashed => self.log.borrow_mut().push("crashed"),
+                CrashMsg::Ping => self.log.borrow_mut().push("ping"),
+            }
+        }
+    }
+
+    impl UpdateNew for Unrestartable {
+        fn new(_relm: &Relm<Self>, log: Rc<RefCell<Vec<&'static str>>>) -> Self {
+            Unrestartable {
+                log,
+            }
+        }
+    }
+
+    #[test]
+    fn supervisor_restart_not_implemented() {
+        let executor = LocalExecutor::new();
+        let log = Rc::new(RefCell::new(vec![]));
+        let component = execute_on::<Unrestartable>(&executor.executor(), log.clone());
+        component.emit(CrashMsg::Crash);
+        component.emit(CrashMsg::Ping);
+        executor.run_until_stalled();
+        // The model cannot be rebuilt, so the component is frozen instead of running with it.
+        assert!(component.stream().is_closed());
+        assert_eq!(*log.borrow(), vec!["close"]);
+    }
+
+    #[test]
+    fn commands() {
+        let mut commands = Commands { count: Rc::new(Cell::new(0)) };
+        match commands.update_with_commands(CommandMsg::Batch) {
+            Cmd::Batch(ref batch) if batch.len() == 2 => (),
+            _ => panic!("expected a batch of 2 commands"),
+        }
+        assert_eq!(commands.count.get(), 0);
+
+        let executor = LocalExecutor::new();
+        let clock = VirtualClock::new();
+        set_clock(clock.clone());
+        let count = Rc::new(Cell::new(0));
+        let component = execute_on::<Commands>(&executor.executor(), count.clone());
+        component.emit(CommandMsg::Batch);
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 3);
+
+        component.emit(CommandMsg::Fetch(Ok(10)));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 13);
+        component.emit(CommandMsg::Fetch(Err(3)));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 10);
+
+        component.emit(CommandMsg::Delay);
+        executor.run_until_stalled();
+        clock.advance(Duration::from_millis(500));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 10);
+        clock.advance(Duration::from_millis(500));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 110);
+
+        component.close();
+        executor.run();
+        assert!(executor.is_empty());
+    }
+
+    #[test]
+    fn component_handle() {
+        let executor = LocalExecutor::new();
+        let component = execute_on::<Counter>(&executor.executor(), Rc::new(Cell::new(0)));
+        let closed = Rc::new(Cell::new(false));
+        {
+            let closed = closed.clone();
+            let future = component.closed().map(move |()| closed.set(true));
+            executor.executor().execute(future).expect("execute");
+        }
+        component.emit(Increment);
+        executor.run_until_stalled();
+        assert_eq!(component.with_model(|count| count.get()), 1);
+        assert!(!closed.get());
+        component.close();
+        executor.run();
+        assert!(closed.get());
+        assert!(executor.is_empty());
+    }
+
+    #[test]
+    fn local_executor() {
+        let executor = LocalExecutor::new();
+        let count = Rc::new(Cell::new(0));
+        let component = execute_on::<Counter>(&executor.executor(), count.clone());
+        component.emit(Increment);
+        component.emit(Increment);
+        assert_eq!(count.get(), 0);
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 2);
+        component.emit(Quit);
+        executor.run();
+        assert!(executor.is_empty());
+    }
+
+    #[test]
+    fn local_executor_timers() {
+        let clock = VirtualClock::new();
+        let executor = LocalExecutor::with_clock(clock.clone());
+        set_clock(executor.clone());
+        let count = Rc::new(Cell::new(0));
+        let relm = Relm::<Counter>::new(executor.executor(), super::EventStream::new());
+        let component = Counter::new(&relm, count.clone());
+        super::init_component(relm.stream(), component, &executor.executor(), &relm);
+        let _ = relm.timeout(Duration::from_millis(20), Increment);
+        let _ = relm.interval(Duration::from_millis(15), Increment);
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 0);
+        clock.advance(Duration::from_millis(15));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 1);
+        clock.advance(Duration::from_millis(5));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 2);
+        clock.advance(Duration::from_millis(10));
+        executor.run_until_stalled();
+        assert_eq!(count.get(), 3);
+        let _ = relm.timeout(Duration::from_millis(10), Quit);
+        clock.advance(Duration::from_millis(10));
+        executor.run();
+        assert_eq!(count.get(), 3);
+        assert!(executor.is_empty());
+    }
+
+    fn received_msgs(relm: &Relm<Counter>) -> Vec<&'static str> {
+        let count = relm.stream().queued_count() as u64;
+        relm.stream().clone().take(count).wait()
+            .map(|msg| msg.expect("message").display_variant())
+            .collect()
+    }
+
+    #[test]
+    fn spawn_blocking() {
+        let executor = LocalExecutor::new();
+        let relm = Relm::<Counter>::new(executor.executor(), super::EventStream::new());
+        let _ = relm.spawn_blocking(|| Ok::<i32, i32>(1), |_| Increment, |_| Decrement);
+        executor.run();
+        let _ = relm.spawn_blocking(|| Err::<i32, i32>(1), |_| Increment, |_| Decrement);
+        executor.run();
+        assert_eq!(received_msgs(&relm), vec!["Increment", "Decrement"]);
+    }
+
+    #[test]
+    fn spawn_blocking_aborted() {
+        // NOTE: with a single worker, the jobs are executed in order.
+        super::init_pool(PoolConfig {
+            name: "test-worker".to_string(),
+            size: 1,
+        });
+        let executor = LocalExecutor::new();
+        let relm = Relm::<Counter>::new(executor.executor(), super::EventStream::new());
+        let (release, released) = mpsc::channel();
+        let _ = relm.spawn_blocking(move || released.recv().map_err(|_| ()), |()| Increment, |()| Decrement);
+        let started = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let started = started.clone();
+            relm.spawn_blocking(move || {
+                started.store(true, Ordering::SeqCst);
+                Ok::<(), ()>(())
+            }, |()| Quit, |()| Quit)
+        };
+        handle.abort();
+        executor.run_until_stalled();
+        release.send(()).expect("send");
+        let _ = relm.spawn_blocking(|| Ok::<(), ()>(()), |()| Increment, |()| Decrement);
+        executor.run();
+        // The aborted job was skipped by the worker since its result was not needed anymore.
+        assert!(!started.load(Ordering::SeqCst));
+        assert_eq!(received_msgs(&relm), vec!["Increment", "Increment"]);
     }
 }
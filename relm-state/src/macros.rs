@@ -25,14 +25,16 @@
 /// 1. Send `$msg` to `$other_component` when the GTK+ `$event` is emitted on `$widget`.
 ///
 /// 2. Optionally send `$msg.0` when the GTK+ `$event` is emitted on `$widget`.
-/// Return `$msg.1` in the GTK+ callback.
-/// This variant gives more control to the caller since it expects a `$msg` returning `(Option<MSG>,
-/// ReturnValue)` where the `ReturnValue` is the value to return in the GTK+ callback.
-/// Option<MSG> can be None if no message needs to be emitted.
+///    Return `$msg.1` in the GTK+ callback.
+///    This variant gives more control to the caller since it expects a `$msg` returning `(Option<MSG>,
+///    ReturnValue)` where the `ReturnValue` is the value to return in the GTK+ callback.
+///    Option<MSG> can be None if no message needs to be emitted.
 ///
 /// 3. Send `$msg` when the GTK+ `$event` is emitted on `$widget`.
 ///
 /// 4. Send `$msg` to `$widget` when the `$message` is received on `$stream`.
+///    This rule returns an `ObserverHandle` which can be used to disconnect the observer.
+///    The observer does not keep the destination alive and is removed when it is closed.
 #[macro_export]
 macro_rules! connect {
     // Connect to a GTK+ widget event, sending a message to another widget.
@@ -61,10 +63,10 @@ macro_rules! connect {
 
     // Connect to a message reception.
     // TODO: create another macro rule accepting multiple patterns.
-    ($src_component:ident @ $message:pat, $dst_component:expr, $msg:expr) => {
+    ($src_component:ident @ $message:pat, $dst_component:expr, $msg:expr) => {{
         let stream = $src_component.stream().clone();
-        connect_stream!(stream@$message, $dst_component.stream(), $msg);
-    };
+        connect_stream!(stream@$message, $dst_component.stream(), $msg)
+    }};
 }
 
 /// Connect events to sending a message.
@@ -74,6 +76,7 @@ macro_rules! connect {
 /// 1. Send `$msg` to `$other_stream` when the GTK+ `$event` is emitted on `$widget`.
 ///
 /// 2. Send `$msg` to `$widget` when the `$message` is received on `$stream`.
+///    This rule returns an `ObserverHandle` which can be used to disconnect the observer.
 #[macro_export]
 macro_rules! connect_stream {
     // Connect to a GTK+ widget event.
@@ -105,9 +108,8 @@ macro_rules! connect_stream {
 
     // Connect to a message reception.
     // TODO: create another macro rule accepting multiple patterns.
-    ($src_stream:ident @ $message:pat, $dst_stream:expr, $msg:expr) => {
-        let stream = $dst_stream.clone();
-        $src_stream.observe(move |msg| {
+    ($src_stream:ident @ $message:pat, $dst_stream:expr, $msg:expr) => {{
+        $src_stream.observe_weak(&$dst_stream, move |stream, msg| {
             #[allow(unreachable_patterns)]
             match msg {
                 &$message =>  {
@@ -118,8 +120,8 @@ macro_rules! connect_stream {
                 },
                 _ => (),
             }
-        });
-    };
+        })
+    }};
 }
 
 /// Connect an asynchronous method call to send a message.
@@ -0,0 +1,107 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use DisplayVariant;
+
+thread_local! {
+    static GLOBAL_MIDDLEWARES: RefCell<Vec<Rc<GlobalMiddleware>>> = RefCell::new(vec![]);
+}
+
+/// A middleware seeing the messages of a component before and after they are handled by its
+/// [`update()`](trait.Update.html#method.update) method.
+///
+/// It is added with [`Relm::add_middleware()`](struct.Relm.html#method.add_middleware).
+pub trait Middleware<MSG> {
+    /// Method called before `msg` is handled.
+    /// It returns the message to handle, which can be a different one, or `None` to drop it.
+    /// A middleware can reroute a message by sending it to another stream and returning `None`.
+    fn before(&self, msg: MSG) -> Option<MSG> {
+        Some(msg)
+    }
+
+    /// Method called after the message whose variant is `msg` was handled in `duration`.
+    fn after(&self, _msg: &'static str, _duration: Duration) {
+    }
+}
+
+/// A middleware seeing the messages of every component before and after they are handled.
+///
+/// It is added with [`add_global_middleware()`](fn.add_global_middleware.html).
+pub trait GlobalMiddleware {
+    /// Method called before `msg`, whose variant is `variant`, is handled.
+    /// The message can be inspected or taken by downcasting it to the message type of a component.
+    /// It returns the message to handle, which can be a different one of the same type, or `None`
+    /// to drop it.
+    /// A message of another type is dropped with an error.
+    fn before(&self, msg: Box<Any>, _variant: &'static str) -> Option<Box<Any>> {
+        Some(msg)
+    }
+
+    /// Method called after the message whose variant is `msg` was handled in `duration`.
+    fn after(&self, _msg: &'static str, _duration: Duration) {
+    }
+}
+
+/// Add a middleware for the components of the current thread.
+/// The global middlewares are called before the middlewares of the component and after them
+/// once the message is handled.
+pub fn add_global_middleware<MIDDLEWARE: GlobalMiddleware + 'static>(middleware: MIDDLEWARE) {
+    GLOBAL_MIDDLEWARES.with(|middlewares| middlewares.borrow_mut().push(Rc::new(middleware)));
+}
+
+/// Call the middlewares before `msg` is handled, returning the message to handle, if any.
+pub fn before<MSG: DisplayVariant + 'static>(middlewares: &[Rc<Middleware<MSG>>], msg: MSG) -> Option<MSG> {
+    // NOTE: clone the middlewares since they might add other middlewares.
+    let global_middlewares = GLOBAL_MIDDLEWARES.with(|middlewares| middlewares.borrow().clone());
+    let mut msg = msg;
+    for middleware in &global_middlewares {
+        let variant = msg.display_variant();
+        let result = middleware.before(Box::new(msg), variant)?;
+        msg =
+            match result.downcast() {
+                Ok(msg) => *msg,
+                Err(_) => {
+                    error!("A global middleware replaced the message {} by a message of another type", variant);
+                    return None;
+                },
+            };
+    }
+    for middleware in middlewares {
+        msg = middleware.before(msg)?;
+    }
+    Some(msg)
+}
+
+/// Call the middlewares after the message whose variant is `msg` was handled in `duration`.
+pub fn after<MSG>(middlewares: &[Rc<Middleware<MSG>>], msg: &'static str, duration: Duration) {
+    for middleware in middlewares.iter().rev() {
+        middleware.after(msg, duration);
+    }
+    let global_middlewares = GLOBAL_MIDDLEWARES.with(|middlewares| middlewares.borrow().clone());
+    for middleware in global_middlewares.iter().rev() {
+        middleware.after(msg, duration);
+    }
+}
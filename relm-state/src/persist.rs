@@ -0,0 +1,167 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Save the state of a component when it is closed and restore it on startup.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use Update;
+use inspector::Inspector;
+
+/// The XDG base directory where the state of a component is saved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PersistLocation {
+    /// `$XDG_CONFIG_HOME`, `~/.config` by default: for settings chosen by the user.
+    Config,
+    /// `$XDG_STATE_HOME`, `~/.local/state` by default: for state like the window size or the
+    /// last opened documents.
+    State,
+}
+
+/// An [`Update`](trait.Update.html) whose state (or part of it) is saved when the component is
+/// closed and restored the next time the model is created.
+///
+/// The persistence is enabled by [`Relm::persist()`](struct.Relm.html#method.persist).
+/// With `#[widget]`, implement `load_state()` and `save_state()` in the widget: the generated
+/// `model()` method then restores the state and the name of the widget struct is the default
+/// `persist_name()`.
+/// Since `#[widget]` implements this trait as soon as one of its methods is found in the widget,
+/// their names are reserved: a widget method named `save_state()`, for instance, is always
+/// considered as `Persist::save_state()`.
+pub trait Persist: Update {
+    /// The saved state.
+    type State: Serialize + DeserializeOwned;
+
+    /// Update the `model` created by [`model()`](trait.Update.html#tymethod.model) with the
+    /// `state` saved by a previous run.
+    fn load_state(model: &mut Self::Model, state: Self::State);
+
+    /// Get the directory where the state is saved.
+    fn persist_location() -> PersistLocation {
+        PersistLocation::State
+    }
+
+    /// Get the name of the file where the state is saved, without extension.
+    /// It must not change between versions of the application, otherwise the saved state is lost.
+    fn persist_name() -> String;
+
+    /// Get the state to save.
+    fn save_state(&self) -> Self::State;
+}
+
+/// Get the file where the state of the component `UPDATE` is saved:
+/// `<XDG directory>/<executable name>/<persist_name()>.json`.
+pub fn persist_path<UPDATE: Persist>() -> Option<PathBuf> {
+    let (variable, default) =
+        match UPDATE::persist_location() {
+            PersistLocation::Config => ("XDG_CONFIG_HOME", ".config"),
+            PersistLocation::State => ("XDG_STATE_HOME", ".local/state"),
+        };
+    // NOTE: relative paths in XDG variables are invalid and must be ignored.
+    let base = env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(default)))?;
+    let app = env::current_exe().ok()
+        .and_then(|exe| exe.file_stem().map(|stem| stem.to_os_string()))?;
+    Some(base.join(app).join(format!("{}.json", UPDATE::persist_name())))
+}
+
+/// Read the state saved for `UPDATE` into `model`.
+pub fn load<UPDATE: Persist>(model: &mut UPDATE::Model) {
+    let path =
+        match persist_path::<UPDATE>() {
+            Some(path) => path,
+            None => return,
+        };
+    let file =
+        match File::open(&path) {
+            Ok(file) => file,
+            // Nothing was saved yet.
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => return,
+            Err(error) => {
+                warn!("Cannot read the state from {}: {}", path.display(), error);
+                return;
+            },
+        };
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(state) => UPDATE::load_state(model, state),
+        Err(error) => warn!("Cannot read the state from {}: {}", path.display(), error),
+    }
+}
+
+/// Write the state of `component`.
+pub fn save<UPDATE: Persist>(component: &UPDATE) {
+    if let Some(path) = persist_path::<UPDATE>() {
+        if let Err(error) = write(&path, &component.save_state()) {
+            error!("Cannot save the state to {}: {}", path.display(), error);
+        }
+    }
+}
+
+fn write<STATE: Serialize>(path: &PathBuf, state: &STATE) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write to a temporary file first to not lose the previous state if the write fails.
+    let temp_path = path.with_extension("json.tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        serde_json::to_writer(&mut writer, state)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        writer.flush()?;
+    }
+    fs::rename(temp_path, path)
+}
+
+/// Inspector saving the state of the component when it is closed.
+pub struct PersistInspector<UPDATE> {
+    _phantom: PhantomData<UPDATE>,
+}
+
+impl<UPDATE> PersistInspector<UPDATE> {
+    /// Create an inspector saving the state of the component when it is closed.
+    pub fn new() -> Self {
+        PersistInspector {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<UPDATE: Persist> Inspector<UPDATE> for PersistInspector<UPDATE> {
+    fn before(&self, _component: &mut UPDATE, _msg: &UPDATE::Msg) {
+    }
+
+    fn after(&self, _component: &UPDATE) {
+    }
+
+    fn close(&self, component: &UPDATE) {
+        save(component);
+    }
+}
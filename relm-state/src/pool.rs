@@ -0,0 +1,128 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+thread_local! {
+    static POOL: RefCell<Option<ThreadPool>> = const { RefCell::new(None) };
+    static POOL_CONFIG: RefCell<PoolConfig> = RefCell::new(PoolConfig::default());
+}
+
+// NOTE: calling a Box<FnOnce()> is not possible, hence this trait.
+trait FnBox {
+    fn call_box(self: Box<Self>);
+}
+
+impl<F: FnOnce()> FnBox for F {
+    fn call_box(self: Box<Self>) {
+        (*self)()
+    }
+}
+
+type Job = Box<FnBox + Send>;
+
+/// The configuration of the worker pool running the closures given to
+/// [`Relm::spawn_blocking()`](struct.Relm.html#method.spawn_blocking).
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// The prefix of the worker thread names, followed by the index of the worker.
+    pub name: String,
+    /// The maximum number of worker threads.
+    pub size: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            name: "relm-worker".to_string(),
+            size: 4,
+        }
+    }
+}
+
+struct ThreadPool {
+    sender: mpsc::Sender<Job>,
+}
+
+impl ThreadPool {
+    fn new(config: &PoolConfig) -> Self {
+        assert!(config.size > 0, "The worker pool needs at least one thread");
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..config.size {
+            let receiver = receiver.clone();
+            let _ = thread::Builder::new()
+                .name(format!("{}-{}", config.name, index))
+                .spawn(move || work(&receiver))
+                .expect("cannot spawn worker thread");
+        }
+        ThreadPool {
+            sender,
+        }
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = {
+            // NOTE: the lock is never poisoned since the jobs are executed after it is released.
+            let receiver = receiver.lock().unwrap();
+            receiver.recv()
+        };
+        match job {
+            Ok(job) => {
+                // A panicking job must not kill the worker: the component is notified by the
+                // dropped result sender.
+                if panic::catch_unwind(AssertUnwindSafe(|| job.call_box())).is_err() {
+                    error!("A closure given to spawn_blocking() panicked");
+                }
+            },
+            // The pool was replaced by init_pool().
+            Err(_) => break,
+        }
+    }
+}
+
+/// Configure the worker pool of the current thread.
+/// If the pool was already started, the current jobs are finished by the old workers and the new
+/// jobs are executed by a new pool.
+pub fn init_pool(config: PoolConfig) {
+    POOL_CONFIG.with(|pool_config| *pool_config.borrow_mut() = config);
+    POOL.with(|pool| *pool.borrow_mut() = None);
+}
+
+/// Execute `job` on the worker pool, starting it if needed.
+pub fn spawn<JOB: FnOnce() + Send + 'static>(job: JOB) {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.is_none() {
+            *pool = Some(POOL_CONFIG.with(|config| ThreadPool::new(&config.borrow())));
+        }
+        if let Some(ref pool) = *pool {
+            // NOTE: the workers never stop while the pool exists, hence unwrap().
+            pool.sender.send(Box::new(job)).unwrap();
+        }
+    });
+}
@@ -0,0 +1,348 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// The default maximum number of events kept for the trace (see
+/// [`set_max_trace_events()`](fn.set_max_trace_events.html)).
+pub const DEFAULT_MAX_TRACE_EVENTS: usize = 100_000;
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static MAX_TRACE_EVENTS: Cell<usize> = const { Cell::new(DEFAULT_MAX_TRACE_EVENTS) };
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::new());
+}
+
+/// The upper bounds, in microseconds, of the buckets of the latency histograms.
+/// The last bucket contains the durations greater than or equal to the last bound.
+pub const HISTOGRAM_BOUNDS: [u64; 6] = [10, 100, 1_000, 10_000, 100_000, 1_000_000];
+
+/// Statistics about the durations of an operation.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    /// The number of times the operation was done.
+    pub count: u64,
+    /// The number of durations in each bucket delimited by [`HISTOGRAM_BOUNDS`](constant.HISTOGRAM_BOUNDS.html).
+    pub histogram: [u64; 7],
+    /// The longest duration.
+    pub max: Duration,
+    /// The sum of the durations.
+    pub total: Duration,
+}
+
+impl Stats {
+    fn add(&mut self, duration: Duration) {
+        let micros = micros(duration);
+        let bucket = HISTOGRAM_BOUNDS.iter()
+            .position(|&bound| micros < bound)
+            .unwrap_or(HISTOGRAM_BOUNDS.len());
+        self.count += 1;
+        self.histogram[bucket] += 1;
+        if duration > self.max {
+            self.max = duration;
+        }
+        self.total += duration;
+    }
+
+    fn to_json(&self, json: &mut String) {
+        let histogram: Vec<_> = self.histogram.iter().map(u64::to_string).collect();
+        let _ = write!(json, "{{\"count\":{},\"histogram\":[{}],\"max_us\":{},\"total_us\":{}}}", self.count,
+            histogram.join(","), micros(self.max), micros(self.total));
+    }
+}
+
+/// The statistics of the messages with the same variant handled by a type of component.
+#[derive(Clone, Debug)]
+pub struct UpdateStats {
+    /// The type of the component.
+    pub component: &'static str,
+    /// The variant of the message.
+    pub msg: &'static str,
+    /// The time spent in the [`update()`](trait.Update.html#method.update) method.
+    pub latency: Stats,
+    /// The time the messages waited in the stream before being handled.
+    pub queue_wait: Stats,
+}
+
+/// The statistics of a property setter generated by the `#[widget]` attribute.
+#[derive(Clone, Debug)]
+pub struct SetterStats {
+    /// The type of the component.
+    pub component: &'static str,
+    /// The name of the setter.
+    pub setter: &'static str,
+    /// The time spent in the setter.
+    pub latency: Stats,
+}
+
+#[derive(Clone, Debug)]
+struct TraceEvent {
+    category: &'static str,
+    component: &'static str,
+    duration: Duration,
+    name: &'static str,
+    start: Duration,
+}
+
+/// The data recorded while profiling.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    events: Vec<TraceEvent>,
+    setters: Vec<SetterStats>,
+    updates: Vec<UpdateStats>,
+}
+
+impl Profile {
+    /// Get the statistics of the generated property setters.
+    pub fn setters(&self) -> &[SetterStats] {
+        &self.setters
+    }
+
+    /// Get the statistics of the messages.
+    pub fn updates(&self) -> &[UpdateStats] {
+        &self.updates
+    }
+
+    /// Export the statistics as JSON.
+    pub fn to_json(&self) -> String {
+        let mut json = "{\"updates\":[".to_string();
+        for (index, stats) in self.updates.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let _ = write!(json, "{{\"component\":\"{}\",\"msg\":\"{}\",\"latency\":", escape(stats.component),
+                escape(stats.msg));
+            stats.latency.to_json(&mut json);
+            json.push_str(",\"queue_wait\":");
+            stats.queue_wait.to_json(&mut json);
+            json.push('}');
+        }
+        json.push_str("],\"setters\":[");
+        for (index, stats) in self.setters.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let _ = write!(json, "{{\"component\":\"{}\",\"setter\":\"{}\",\"latency\":", escape(stats.component),
+                escape(stats.setter));
+            stats.latency.to_json(&mut json);
+            json.push('}');
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// Export the recorded events in the Chrome trace format, which can be opened in
+    /// `chrome://tracing`.
+    /// Only the last events are kept (see [`set_max_trace_events()`](fn.set_max_trace_events.html)).
+    pub fn to_chrome_trace(&self) -> String {
+        let mut json = "{\"traceEvents\":[".to_string();
+        for (index, event) in self.events.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let _ = write!(json,
+                "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":1,\"tid\":1,\
+                \"args\":{{\"component\":\"{}\"}}}}",
+                escape(event.name), event.category, micros(event.start), micros(event.duration),
+                escape(event.component));
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+struct Profiler {
+    events: VecDeque<TraceEvent>,
+    setters: HashMap<(&'static str, &'static str), Stats>,
+    start: Instant,
+    updates: HashMap<(&'static str, &'static str), (Stats, Stats)>,
+}
+
+impl Profiler {
+    fn new() -> Self {
+        Profiler {
+            events: VecDeque::new(),
+            setters: HashMap::new(),
+            start: Instant::now(),
+            updates: HashMap::new(),
+        }
+    }
+
+    fn add_event(&mut self, category: &'static str, component: &'static str, name: &'static str, start: Instant,
+        duration: Duration)
+    {
+        let start =
+            if start > self.start {
+                start - self.start
+            }
+            else {
+                Duration::from_secs(0)
+            };
+        let max_events = MAX_TRACE_EVENTS.with(Cell::get);
+        while self.events.len() >= max_events && !self.events.is_empty() {
+            let _ = self.events.pop_front();
+        }
+        if max_events > 0 {
+            self.events.push_back(TraceEvent {
+                category,
+                component,
+                duration,
+                name,
+                start,
+            });
+        }
+    }
+}
+
+/// A guard measuring the time spent in a generated property setter until it is dropped.
+pub struct SetterProfile {
+    component: &'static str,
+    setter: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for SetterProfile {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            let duration = start.elapsed();
+            PROFILER.with(|profiler| {
+                let mut profiler = profiler.borrow_mut();
+                profiler.setters.entry((self.component, self.setter))
+                    .or_insert_with(Stats::default)
+                    .add(duration);
+                profiler.add_event("setter", self.component, self.setter, start, duration);
+            });
+        }
+    }
+}
+
+/// Check whether the profiling is enabled on the current thread.
+pub fn is_profiling() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Get the data recorded since the profiling started or since the last call to
+/// [`reset_profile()`](fn.reset_profile.html).
+pub fn profile() -> Profile {
+    PROFILER.with(|profiler| {
+        let profiler = profiler.borrow();
+        let mut updates: Vec<_> = profiler.updates.iter()
+            .map(|(&(component, msg), (latency, queue_wait))| UpdateStats {
+                component,
+                msg,
+                latency: latency.clone(),
+                queue_wait: queue_wait.clone(),
+            })
+            .collect();
+        updates.sort_by_key(|stats| (stats.component, stats.msg));
+        let mut setters: Vec<_> = profiler.setters.iter()
+            .map(|(&(component, setter), latency)| SetterStats {
+                component,
+                setter,
+                latency: latency.clone(),
+            })
+            .collect();
+        setters.sort_by_key(|stats| (stats.component, stats.setter));
+        Profile {
+            events: profiler.events.iter().cloned().collect(),
+            setters,
+            updates,
+        }
+    })
+}
+
+#[doc(hidden)]
+pub fn profile_setter(component: &'static str, setter: &'static str) -> SetterProfile {
+    SetterProfile {
+        component,
+        setter,
+        start:
+            if is_profiling() {
+                Some(Instant::now())
+            }
+            else {
+                None
+            },
+    }
+}
+
+/// Record a message handled by the `update()` method of a component.
+pub fn record_update(component: &'static str, msg: &'static str, start: Instant, duration: Duration,
+    queue_wait: Option<Duration>)
+{
+    PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        {
+            let stats = profiler.updates.entry((component, msg))
+                .or_insert_with(Default::default);
+            stats.0.add(duration);
+            if let Some(queue_wait) = queue_wait {
+                stats.1.add(queue_wait);
+            }
+        }
+        profiler.add_event("update", component, msg, start, duration);
+    });
+}
+
+/// Discard the recorded data.
+pub fn reset_profile() {
+    PROFILER.with(|profiler| *profiler.borrow_mut() = Profiler::new());
+}
+
+/// Set the maximum number of events kept for the trace of the current thread.
+/// When this number is reached, the oldest events are discarded so that the memory used by the
+/// profiler stays bounded.
+/// The statistics are not affected by this limit.
+pub fn set_max_trace_events(max_events: usize) {
+    MAX_TRACE_EVENTS.with(|max| max.set(max_events));
+}
+
+/// Enable or disable the profiling of the components of the current thread.
+/// It records the time spent in the [`update()`](trait.Update.html#method.update) methods and in
+/// the property setters generated by the `#[widget]` attribute.
+pub fn set_profiling(enabled: bool) {
+    ENABLED.with(|profiling| profiling.set(enabled));
+}
+
+fn escape(string: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+    for character in string.chars() {
+        match character {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            character if character.is_control() => {
+                let _ = write!(result, "\\u{:04x}", character as u32);
+            },
+            character => result.push(character),
+        }
+    }
+    result
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + duration.subsec_nanos() as u64 / 1_000
+}
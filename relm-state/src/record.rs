@@ -0,0 +1,180 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Record the messages sent to components and replay them later.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+use relm_core::{EventStream, ObserverHandle};
+
+use {Relm, Update};
+use clock::clock;
+
+/// A message read from a recording.
+pub struct Record<MSG> {
+    /// The time elapsed between the creation of the `Recorder` and the emission of the message.
+    pub elapsed: Duration,
+    /// The message.
+    pub msg: MSG,
+    /// The path of the component which received the message.
+    pub path: String,
+}
+
+/// Write every message sent to the recorded components in a file, one JSON object per line.
+///
+/// The recorder can be cloned to record multiple components in the same file.
+#[derive(Clone)]
+pub struct Recorder {
+    start: Instant,
+    writer: Rc<RefCell<Box<Write>>>,
+}
+
+impl Recorder {
+    /// Create a recorder writing to the file at `path`.
+    /// The file is truncated if it already exists.
+    /// Every message is flushed to the file as soon as it is recorded, so that the recording is
+    /// complete even if the application crashes.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self::from_writer(LineWriter::new(file)))
+    }
+
+    /// Create a recorder writing to `writer`.
+    /// If `writer` is buffered, the messages are only written when it is flushed (see
+    /// [`flush()`](#method.flush)).
+    pub fn from_writer<WRITER: Write + 'static>(writer: WRITER) -> Self {
+        Recorder {
+            start: Instant::now(),
+            writer: Rc::new(RefCell::new(Box::new(writer))),
+        }
+    }
+
+    /// Flush the messages written so far.
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer.borrow_mut().flush()
+    }
+
+    /// Record every message emitted on `stream`, tagged with the component `path`.
+    /// The recording stops when the returned handle is disconnected or when the stream is dropped.
+    pub fn record<MSG>(&self, path: &str, stream: &EventStream<MSG>) -> ObserverHandle<MSG>
+        where MSG: Serialize + 'static,
+    {
+        let recorder = self.clone();
+        let path = path.to_string();
+        stream.observe(move |msg| {
+            if let Err(error) = recorder.write(&path, msg) {
+                error!("Cannot record message for component {}: {}", path, error);
+            }
+        })
+    }
+
+    fn write<MSG: Serialize>(&self, path: &str, msg: &MSG) -> io::Result<()> {
+        let elapsed = self.start.elapsed();
+        let elapsed = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_micros());
+        let path = serde_json::to_string(path).map_err(invalid_data)?;
+        let msg = serde_json::to_string(msg).map_err(invalid_data)?;
+        writeln!(self.writer.borrow_mut(), "{{\"elapsed_us\":{},\"path\":{},\"msg\":{}}}", elapsed, path, msg)
+    }
+}
+
+/// The messages of a component read from a recording.
+pub struct Replay<MSG> {
+    records: Vec<Record<MSG>>,
+}
+
+impl<MSG: DeserializeOwned> Replay<MSG> {
+    /// Read the messages of the component `path` from the file at `file`.
+    pub fn load<P: AsRef<Path>>(file: P, path: &str) -> io::Result<Self> {
+        let file = File::open(file)?;
+        Self::from_reader(BufReader::new(file), path)
+    }
+
+    /// Read the messages of the component `path` from `reader`.
+    pub fn from_reader<READER: BufRead>(reader: READER, path: &str) -> io::Result<Self> {
+        let mut records = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value: Value = serde_json::from_str(&line).map_err(invalid_data)?;
+            let record_path = value.get("path").and_then(Value::as_str)
+                .ok_or_else(|| invalid_data("missing path"))?;
+            if record_path != path {
+                continue;
+            }
+            let elapsed = value.get("elapsed_us").and_then(Value::as_u64)
+                .ok_or_else(|| invalid_data("missing elapsed_us"))?;
+            let msg = value.get("msg").cloned()
+                .ok_or_else(|| invalid_data("missing msg"))?;
+            records.push(Record {
+                elapsed: Duration::new(elapsed / 1_000_000, (elapsed % 1_000_000) as u32 * 1_000),
+                msg: serde_json::from_value(msg).map_err(invalid_data)?,
+                path: record_path.to_string(),
+            });
+        }
+        Ok(Replay {
+            records,
+        })
+    }
+}
+
+impl<MSG> Replay<MSG> {
+    /// Get the messages read from the recording.
+    pub fn records(&self) -> &[Record<MSG>] {
+        &self.records
+    }
+
+    /// Send all the messages to `stream` right away, in the order they were recorded.
+    pub fn replay(self, stream: &EventStream<MSG>) {
+        for record in self.records {
+            stream.emit(record.msg);
+        }
+    }
+
+    /// Send the messages to the component at the same pace they were recorded, as measured by
+    /// the [`clock()`](fn.clock.html).
+    pub fn replay_timed<UPDATE>(self, relm: &Relm<UPDATE>)
+        where UPDATE: Update<Msg=MSG>,
+              MSG: 'static,
+    {
+        let start = clock().now();
+        for record in self.records {
+            let _ = relm.emit_at(start + record.elapsed, record.msg);
+        }
+    }
+}
+
+fn invalid_data<ERROR>(error: ERROR) -> io::Error
+    where ERROR: Into<Box<Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,153 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::time::Duration;
+
+use futures::{Future, Stream};
+use relm_core::EventStream;
+
+use {AbortHandle, Relm, Update};
+use clock::clock;
+
+type Start<MSG> = Box<Fn(&EventStream<MSG>) -> Box<Future<Item=(), Error=()>>>;
+
+/// A keyed set of subscriptions returned by
+/// [`Update::dynamic_subscriptions()`](trait.Update.html#method.dynamic_subscriptions).
+///
+/// A subscription is started when its key appears in the set and cancelled when its key
+/// disappears from it.
+/// Since a subscription whose key is still in the set is not restarted, the key should change
+/// with the parameters of the subscription.
+pub struct Subscriptions<MSG> {
+    subscriptions: Vec<Subscription<MSG>>,
+}
+
+struct Subscription<MSG> {
+    key: String,
+    // Whether the subscription is started again when it ended while its key is still in the set.
+    restart: bool,
+    start: Start<MSG>,
+}
+
+impl<MSG> Subscriptions<MSG> {
+    /// Create an empty set of subscriptions.
+    pub fn new() -> Self {
+        Subscriptions {
+            subscriptions: vec![],
+        }
+    }
+}
+
+impl<MSG> Default for Subscriptions<MSG> {
+    fn default() -> Self {
+        Subscriptions::new()
+    }
+}
+
+impl<MSG: 'static> Subscriptions<MSG> {
+    /// Add a subscription sending `msg` every `duration`.
+    pub fn interval<KEY: Into<String>>(self, key: KEY, duration: Duration, msg: MSG) -> Self
+        where MSG: Clone,
+    {
+        self.stream(key, move || {
+            let msg = msg.clone();
+            clock().interval(duration)
+                .map(move |()| msg.clone())
+        })
+    }
+
+    /// Add a subscription sending the messages of the stream created by `create_stream`, until it
+    /// ends or fails.
+    /// Once ended, the subscription is not started again until its key is removed from the set and
+    /// added back.
+    pub fn stream<CREATE, KEY, STREAM>(self, key: KEY, create_stream: CREATE) -> Self
+        where CREATE: Fn() -> STREAM + 'static,
+              KEY: Into<String>,
+              STREAM: Stream<Item=MSG> + 'static,
+    {
+        self.add(key.into(), false, create_stream)
+    }
+
+    /// Add a subscription sending the messages of the stream created by `create_stream`, until it
+    /// ends or fails.
+    /// If its key is still in the set after the next update, the stream is created again, e.g. to
+    /// reconnect to a server.
+    pub fn restarting_stream<CREATE, KEY, STREAM>(self, key: KEY, create_stream: CREATE) -> Self
+        where CREATE: Fn() -> STREAM + 'static,
+              KEY: Into<String>,
+              STREAM: Stream<Item=MSG> + 'static,
+    {
+        self.add(key.into(), true, create_stream)
+    }
+
+    fn add<CREATE, STREAM>(mut self, key: String, restart: bool, create_stream: CREATE) -> Self
+        where CREATE: Fn() -> STREAM + 'static,
+              STREAM: Stream<Item=MSG> + 'static,
+    {
+        let start = move |stream: &EventStream<MSG>| -> Box<Future<Item=(), Error=()>> {
+            let stream = stream.clone();
+            let future = create_stream()
+                .map_err(|_| ())
+                .for_each(move |msg| {
+                    stream.emit(msg);
+                    Ok(())
+                });
+            Box::new(future)
+        };
+        self.subscriptions.push(Subscription {
+            key,
+            restart,
+            start: Box::new(start),
+        });
+        self
+    }
+}
+
+/// Start the subscriptions of `component` which are not `running` and cancel the ones which were
+/// removed.
+pub fn sync<UPDATE>(component: &UPDATE, relm: &Relm<UPDATE>, running: &mut HashMap<String, AbortHandle>)
+    where UPDATE: Update,
+          UPDATE::Msg: 'static,
+{
+    let subscriptions = component.dynamic_subscriptions().subscriptions;
+    if subscriptions.is_empty() && running.is_empty() {
+        return;
+    }
+    // NOTE: the subscriptions which ended are kept as done (so that they are not started over and
+    // over), unless they restart.
+    let removed: Vec<_> = running.iter()
+        .filter(|&(key, handle)| !subscriptions.iter().any(|subscription|
+            &subscription.key == key && !(subscription.restart && handle.is_finished())))
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in removed {
+        if let Some(handle) = running.remove(&key) {
+            handle.abort();
+        }
+    }
+    for subscription in subscriptions {
+        if let Entry::Vacant(entry) = running.entry(subscription.key) {
+            let _ = entry.insert(relm.exec((subscription.start)(relm.stream())));
+        }
+    }
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use {DisplayVariant, Relm, Update};
+
+thread_local! {
+    static PANIC_HOOK: RefCell<Option<PanicHook>> = const { RefCell::new(None) };
+}
+
+type PanicHook = Rc<Fn(&Panic)>;
+
+/// What happens when the [`update()`](trait.Update.html#method.update) method of a component
+/// panics.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PanicPolicy {
+    /// Propagate the panic, which usually terminates the application.
+    Abort,
+    /// Close the stream of the component so that it does not handle messages anymore.
+    Freeze,
+    /// Call [`Update::restart()`](trait.Update.html#method.restart) and continue handling the
+    /// next messages.
+    /// The component is frozen instead if it does not implement this method.
+    Restart,
+}
+
+/// A panic caught in the [`update()`](trait.Update.html#method.update) method of a component.
+#[derive(Clone, Debug)]
+pub struct Panic {
+    /// The variant of the message that was handled.
+    pub msg: &'static str,
+    /// The message of the panic.
+    pub reason: String,
+}
+
+/// Set the function called when the [`update()`](trait.Update.html#method.update) method of a
+/// component whose policy is not `PanicPolicy::Abort` panics.
+pub fn set_panic_hook<CALLBACK: Fn(&Panic) + 'static>(callback: CALLBACK) {
+    PANIC_HOOK.with(|hook| *hook.borrow_mut() = Some(Rc::new(callback)));
+}
+
+/// Call the `update()` method of the component with `update`, applying the panic policy of the
+/// component if it panics.
+pub fn supervise<UPDATE, UPDATEFN>(component: &mut UPDATE, relm: &Relm<UPDATE>, event: UPDATE::Msg,
+    update: UPDATEFN)
+    where UPDATE: Update,
+          UPDATE::Msg: 'static,
+          UPDATEFN: FnOnce(&mut UPDATE, &Relm<UPDATE>, UPDATE::Msg),
+{
+    let policy = UPDATE::panic_policy();
+    if policy == PanicPolicy::Abort {
+        update(component, relm, event);
+        return;
+    }
+    let msg = event.display_variant();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| update(component, relm, event)));
+    if let Err(payload) = result {
+        let panic = Panic {
+            msg,
+            reason: reason(&*payload),
+        };
+        error!("The update function panicked for message {}: {}", panic.msg, panic.reason);
+        let hook = PANIC_HOOK.with(|hook| hook.borrow().clone());
+        if let Some(hook) = hook {
+            hook(&panic);
+        }
+        if let Some(msg) = UPDATE::panic_msg(&panic) {
+            // NOTE: only send the message to the observers, so that the component does not
+            // receive the message about its own panic.
+            relm.stream().notify_observers(&msg);
+        }
+        match policy {
+            PanicPolicy::Abort => unreachable!(),
+            PanicPolicy::Freeze => {
+                let _ = relm.stream().close();
+            },
+            PanicPolicy::Restart => component.restart(relm),
+        }
+    }
+}
+
+fn reason(payload: &(Any + Send)) -> String {
+    if let Some(reason) = payload.downcast_ref::<&str>() {
+        reason.to_string()
+    }
+    else if let Some(reason) = payload.downcast_ref::<String>() {
+        reason.clone()
+    }
+    else {
+        "unknown reason".to_string()
+    }
+}
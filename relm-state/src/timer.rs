@@ -0,0 +1,139 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures::{Future, Stream};
+use futures::future::Executor as FutureExecutor;
+use relm_core::EventStream;
+
+use {AbortHandle, Executor};
+use abort;
+use clock;
+
+/// The function spawning the timeouts of a debouncer or a throttler.
+pub type Spawn = Rc<Fn(Box<Future<Item=(), Error=()>>)>;
+
+struct Throttle<MSG> {
+    active: bool,
+    pending: Option<MSG>,
+}
+
+/// Create a stream sending its last message to `stream` once no other message was emitted on it
+/// for `duration`.
+pub fn debounce<MSG: 'static>(executor: &Executor, stream: &EventStream<MSG>, duration: Duration)
+    -> EventStream<MSG>
+{
+    let (input, future) = debouncer(executor_spawn(executor), stream, duration);
+    // NOTE: no error can be returned from execute(), hence unwrap().
+    executor.execute(future).unwrap();
+    input
+}
+
+/// Create the input stream of a debouncer and the future dispatching its messages.
+/// The timeouts are spawned with `spawn`.
+pub fn debouncer<MSG: 'static>(spawn: Spawn, stream: &EventStream<MSG>, duration: Duration)
+    -> (EventStream<MSG>, Box<Future<Item=(), Error=()>>)
+{
+    let input = EventStream::new();
+    let mut last_timeout: Option<AbortHandle> = None;
+    let stream = stream.clone();
+    let future = input.clone().for_each(move |msg| {
+        // Every message cancels the timeout started by the previous message.
+        if let Some(handle) = last_timeout.take() {
+            handle.abort();
+        }
+        let stream = stream.clone();
+        let timeout = clock::timeout(duration)
+            .map(move |()| stream.emit(msg));
+        let (timeout, handle) = abort::abortable(timeout);
+        last_timeout = Some(handle);
+        spawn(Box::new(timeout));
+        Ok(())
+    });
+    (input, Box::new(future))
+}
+
+/// Create a stream sending at most one message to `stream` every `duration`.
+/// The first message is sent right away and the last message emitted during `duration` is sent
+/// at the end of it.
+pub fn throttle<MSG: 'static>(executor: &Executor, stream: &EventStream<MSG>, duration: Duration)
+    -> EventStream<MSG>
+{
+    let (input, future) = throttler(executor_spawn(executor), stream, duration);
+    // NOTE: no error can be returned from execute(), hence unwrap().
+    executor.execute(future).unwrap();
+    input
+}
+
+/// Create the input stream of a throttler and the future dispatching its messages.
+/// The timeouts are spawned with `spawn`.
+pub fn throttler<MSG: 'static>(spawn: Spawn, stream: &EventStream<MSG>, duration: Duration)
+    -> (EventStream<MSG>, Box<Future<Item=(), Error=()>>)
+{
+    let input = EventStream::new();
+    let state = Rc::new(RefCell::new(Throttle {
+        active: false,
+        pending: None,
+    }));
+    let stream = stream.clone();
+    let future = input.clone().for_each(move |msg| {
+        let active = state.borrow().active;
+        if active {
+            state.borrow_mut().pending = Some(msg);
+        }
+        else {
+            state.borrow_mut().active = true;
+            stream.emit(msg);
+            start_throttle_window(&spawn, &state, &stream, duration);
+        }
+        Ok(())
+    });
+    (input, Box::new(future))
+}
+
+fn executor_spawn(executor: &Executor) -> Spawn {
+    let executor = executor.clone();
+    // NOTE: no error can be returned from execute(), hence unwrap().
+    Rc::new(move |future| executor.execute(future).unwrap())
+}
+
+fn start_throttle_window<MSG: 'static>(spawn: &Spawn, state: &Rc<RefCell<Throttle<MSG>>>,
+    stream: &EventStream<MSG>, duration: Duration)
+{
+    let state = state.clone();
+    let stream = stream.clone();
+    let timer_spawn = spawn.clone();
+    let timeout = clock::timeout(duration)
+        .map(move |()| {
+            let pending = state.borrow_mut().pending.take();
+            match pending {
+                Some(msg) => {
+                    stream.emit(msg);
+                    start_throttle_window(&timer_spawn, &state, &stream, duration);
+                },
+                None => state.borrow_mut().active = false,
+            }
+        });
+    spawn(Box::new(timeout));
+}
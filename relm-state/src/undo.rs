@@ -0,0 +1,176 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Undo and redo the messages handled by a component.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use Update;
+use inspector::Inspector;
+
+/// How a message interacts with the undo stack.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UndoKind {
+    /// The message does not change the state to undo.
+    Ignore,
+    /// The message can be undone by itself.
+    Record,
+    /// The message is undone together with the previous consecutive messages of the same group.
+    Group(&'static str),
+    /// The message restores the state before the last undoable step.
+    Undo,
+    /// The message restores the state undone by the last undo.
+    Redo,
+}
+
+/// An [`Update`](trait.Update.html) whose state can be restored by undo and redo messages.
+///
+/// The undo stack is enabled by [`Relm::enable_undo()`](struct.Relm.html#method.enable_undo).
+/// With `#[widget]`, implement `undo_kind()` (and optionally `undo_depth()`) in the widget: the
+/// snapshot is then the model and restoring it updates the view.
+pub trait Undoable: Update {
+    /// The state saved before every undoable message.
+    type Snapshot;
+
+    /// Get the maximum number of steps that can be undone.
+    fn undo_depth() -> usize {
+        100
+    }
+
+    /// Tell how `msg` interacts with the undo stack.
+    fn undo_kind(msg: &Self::Msg) -> UndoKind;
+
+    /// Restore the state saved by [`snapshot()`](#tymethod.snapshot).
+    fn restore(&mut self, snapshot: Self::Snapshot);
+
+    /// Save the state to restore when undoing a message.
+    fn snapshot(&self) -> Self::Snapshot;
+}
+
+struct Inner<SNAPSHOT> {
+    depth: usize,
+    group: Option<&'static str>,
+    redo: Vec<SNAPSHOT>,
+    undo: VecDeque<SNAPSHOT>,
+}
+
+/// The undo and redo stacks of a component.
+pub struct UndoStack<SNAPSHOT> {
+    inner: Rc<RefCell<Inner<SNAPSHOT>>>,
+}
+
+impl<SNAPSHOT> Clone for UndoStack<SNAPSHOT> {
+    fn clone(&self) -> Self {
+        UndoStack {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<SNAPSHOT> UndoStack<SNAPSHOT> {
+    /// Create an undo stack keeping at most `depth` steps.
+    pub fn new(depth: usize) -> Self {
+        UndoStack {
+            inner: Rc::new(RefCell::new(Inner {
+                depth,
+                group: None,
+                redo: vec![],
+                undo: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Check if a step can be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.inner.borrow().redo.is_empty()
+    }
+
+    /// Check if a step can be undone.
+    pub fn can_undo(&self) -> bool {
+        !self.inner.borrow().undo.is_empty()
+    }
+
+    /// Remove all the steps.
+    pub fn clear(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.group = None;
+        inner.redo.clear();
+        inner.undo.clear();
+    }
+
+    /// End the current group, so that the next message of the same group starts a new step.
+    pub fn end_group(&self) {
+        self.inner.borrow_mut().group = None;
+    }
+
+    fn push(&self, snapshot: SNAPSHOT) {
+        let mut inner = self.inner.borrow_mut();
+        inner.redo.clear();
+        inner.undo.push_back(snapshot);
+        while inner.undo.len() > inner.depth {
+            let _ = inner.undo.pop_front();
+        }
+    }
+}
+
+impl<UPDATE: Undoable> Inspector<UPDATE> for UndoStack<UPDATE::Snapshot> {
+    fn before(&self, component: &mut UPDATE, msg: &UPDATE::Msg) {
+        let kind = UPDATE::undo_kind(msg);
+        let group = self.inner.borrow().group;
+        match kind {
+            UndoKind::Ignore => (),
+            UndoKind::Group(name) => {
+                if group != Some(name) {
+                    self.push(component.snapshot());
+                }
+                self.inner.borrow_mut().group = Some(name);
+            },
+            UndoKind::Record => {
+                self.push(component.snapshot());
+                self.end_group();
+            },
+            UndoKind::Undo => {
+                self.end_group();
+                // NOTE: do not borrow while calling the component since it could use the stack.
+                let snapshot = self.inner.borrow_mut().undo.pop_back();
+                if let Some(snapshot) = snapshot {
+                    let current = component.snapshot();
+                    self.inner.borrow_mut().redo.push(current);
+                    component.restore(snapshot);
+                }
+            },
+            UndoKind::Redo => {
+                self.end_group();
+                let snapshot = self.inner.borrow_mut().redo.pop();
+                if let Some(snapshot) = snapshot {
+                    let current = component.snapshot();
+                    self.inner.borrow_mut().undo.push_back(current);
+                    component.restore(snapshot);
+                }
+            },
+        }
+    }
+
+    fn after(&self, _component: &UPDATE) {
+    }
+}
@@ -19,12 +19,20 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
-use super::{EventStream, Widget};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::WidgetExt;
+
+use super::{EventStream, ModelRef, Priority, Widget};
 
 /// Widget that was added by the `ContainerWidget::add_widget()` method.
 ///
 /// ## Warning
-/// You must keep your components as long as you want them to send/receive events.
+/// The component stops receiving events when its root widget is destroyed, or when the
+/// `Component` is dropped while its root widget is not in a container (e.g. a window).
+/// Hence, you must keep your components as long as you want them to send/receive events when they
+/// are not added to a container.
 /// Common practice is to store `Component`s in the `Widget` struct (see the [communication
 /// example](https://github.com/antoyo/relm/blob/master/examples/communication.rs#L182-L188)).
 /// The `#[widget]` attribute takes care of storing them in the struct automatically (see the
@@ -32,20 +40,27 @@ use super::{EventStream, Widget};
 #[must_use]
 #[derive(Clone)]
 pub struct Component<WIDGET: Widget> {
+    component: Rc<RefCell<WIDGET>>,
     stream: EventStream<WIDGET::Msg>,
     widget: WIDGET::Root,
 }
 
 impl<WIDGET: Widget> Drop for Component<WIDGET> {
     fn drop(&mut self) {
-        let _ = self.stream.close();
+        // A widget which is in a container keeps its stream: it is closed when the widget is
+        // destroyed.
+        // NOTE: the stream might already be closed, e.g. when the window was destroyed.
+        if self.widget.get_parent().is_none() && !self.stream.is_closed() {
+            let _ = self.stream.close();
+        }
     }
 }
 
 impl<WIDGET: Widget> Component<WIDGET> {
     #[doc(hidden)]
-    pub fn new(stream: EventStream<WIDGET::Msg>, widget: WIDGET::Root) -> Self {
+    pub fn new(stream: EventStream<WIDGET::Msg>, widget: WIDGET::Root, component: Rc<RefCell<WIDGET>>) -> Self {
         Component {
+            component,
             stream,
             widget,
         }
@@ -56,6 +71,11 @@ impl<WIDGET: Widget> Component<WIDGET> {
         self.stream.emit(msg);
     }
 
+    /// Emit a message of the widget stream with the specified `priority`.
+    pub fn emit_with_priority(&self, msg: WIDGET::Msg, priority: Priority) {
+        self.stream.emit_with_priority(msg, priority);
+    }
+
     /// Get the event stream of the component.
     /// This is used internally by the library.
     pub fn stream(&self) -> &EventStream<WIDGET::Msg> {
@@ -66,4 +86,15 @@ impl<WIDGET: Widget> Component<WIDGET> {
     pub fn widget(&self) -> &WIDGET::Root {
         &self.widget
     }
+
+    /// Call `callback` with the model of the component.
+    ///
+    /// ## Panics
+    /// Panics when called from the `update()` method of this component.
+    pub fn with_model<CALLBACK, VALUE>(&self, callback: CALLBACK) -> VALUE
+        where CALLBACK: FnOnce(&WIDGET::Model) -> VALUE,
+              WIDGET: ModelRef,
+    {
+        callback(self.component.borrow().model_ref())
+    }
 }
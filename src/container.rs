@@ -23,8 +23,8 @@ use glib::Cast;
 use gtk;
 use gtk::{ContainerExt, IsA, Object, WidgetExt};
 
-use relm_state::EventStream;
-use super::{Component, DisplayVariant, Relm, create_widget, init_component};
+use relm_state::{EventStream, ModelRef, Priority};
+use super::{Component, DisplayVariant, Relm, create_widget, init_widget};
 use widget::Widget;
 
 /// Struct for relm containers to add GTK+ and relm `Widget`s.
@@ -60,8 +60,8 @@ impl<WIDGET: Container + Widget> ContainerComponent<WIDGET> {
     {
         let (widget, component, child_relm) = create_widget::<CHILDWIDGET>(relm.executor(), model_param);
         let container = WIDGET::add_widget(self, &widget);
-        component.on_add(container);
-        init_component::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
+        component.borrow().on_add(container);
+        init_widget::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
         widget
     }
 
@@ -70,6 +70,11 @@ impl<WIDGET: Container + Widget> ContainerComponent<WIDGET> {
         self.stream().emit(msg);
     }
 
+    /// Emit a message of the widget stream with the specified `priority`.
+    pub fn emit_with_priority(&self, msg: WIDGET::Msg, priority: Priority) {
+        self.stream().emit_with_priority(msg, priority);
+    }
+
     /// Get the event stream of the component.
     /// This is used internally by the library.
     pub fn stream(&self) -> &EventStream<WIDGET::Msg> {
@@ -82,6 +87,14 @@ impl<WIDGET: Container + Widget> ContainerComponent<WIDGET> {
     pub fn widget(&self) -> &WIDGET::Root {
         self.component.widget()
     }
+
+    /// Call `callback` with the model of the component.
+    pub fn with_model<CALLBACK, VALUE>(&self, callback: CALLBACK) -> VALUE
+        where CALLBACK: FnOnce(&WIDGET::Model) -> VALUE,
+              WIDGET: ModelRef,
+    {
+        self.component.with_model(callback)
+    }
 }
 
 /// Trait to implement relm container widget.
@@ -153,12 +166,12 @@ impl<W: Clone + ContainerExt + IsA<gtk::Widget> + IsA<Object>> ContainerWidget f
               WIDGET: Widget,
     {
         let (widget, component, child_relm) = create_widget::<CHILDWIDGET>(relm.executor(), model_param);
-        let container = component.container().clone();
-        let containers = component.other_containers();
-        let root = component.root().clone();
+        let container = component.borrow().container().clone();
+        let containers = component.borrow().other_containers();
+        let root = component.borrow().root().clone();
         self.add(&root);
-        component.on_add(self.clone());
-        init_component::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
+        component.borrow().on_add(self.clone());
+        init_widget::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
         ContainerComponent::new(widget, container, containers)
     }
 
@@ -171,8 +184,8 @@ impl<W: Clone + ContainerExt + IsA<gtk::Widget> + IsA<Object>> ContainerWidget f
     {
         let (widget, component, child_relm) = create_widget::<CHILDWIDGET>(relm.executor(), model_param);
         self.add(widget.widget());
-        component.on_add(self.clone());
-        init_component::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
+        component.borrow().on_add(self.clone());
+        init_widget::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
         widget
     }
 
@@ -108,6 +108,7 @@
  * TODO: try tk-easyloop in another branch.
  */
 
+extern crate futures;
 extern crate futures_glib;
 extern crate glib;
 extern crate glib_sys;
@@ -123,7 +124,14 @@ mod container;
 mod macros;
 mod widget;
 
-use futures_glib::Executor;
+use std::cell::{Cell, RefCell};
+#[cfg(feature = "record")]
+use std::io;
+#[cfg(feature = "record")]
+use std::path::Path;
+use std::rc::{Rc, Weak};
+
+use futures::future;
 #[doc(hidden)]
 pub use futures_glib::MainLoop;
 #[doc(hidden)]
@@ -136,20 +144,76 @@ use glib_sys::GType;
 #[doc(hidden)]
 pub use gobject_sys::{GParameter, g_object_newv};
 use gobject_sys::{GObject, GValue};
+use gtk::WidgetExt;
 use libc::{c_char, c_uint};
 #[doc(hidden)]
 pub use relm_core::EventStream;
 pub use relm_state::{
+    AbortHandle,
+    Clock,
+    Closed,
+    Cmd,
+    ComponentHandle,
     DisplayVariant,
+    Executor,
+    GlibSpawner,
+    GlobalMiddleware,
+    History,
     IntoOption,
     IntoPair,
+    Middleware,
+    ModelRef,
+    ObserverGuard,
+    ObserverHandle,
+    OverflowPolicy,
+    Panic,
+    PanicPolicy,
+    PausePolicy,
+    PoolConfig,
+    Priority,
+    Profile,
     Relm,
+    Reply,
+    Response,
+    Sender,
+    SetterProfile,
+    SetterStats,
+    Stats,
+    Subscriptions,
+    UndoKind,
+    UndoStack,
+    Undoable,
     Update,
     UpdateNew,
+    UpdateStats,
+    VirtualClock,
+    add_global_middleware,
+    clock,
     create_executor,
+    create_executor_with_priority,
     execute,
+    init_pool,
+    is_profiling,
+    profile,
+    reset_profile,
+    set_clock,
+    set_max_trace_events,
+    set_panic_hook,
+    set_profiling,
 };
-use relm_state::init_component;
+#[doc(hidden)]
+pub use relm_state::profile_setter;
+#[cfg(feature = "persist")]
+pub use relm_state::{Persist, PersistLocation, persist_path};
+#[cfg(feature = "record")]
+pub use relm_state::{Record, Recorder, Replay};
+// NOTE: used by the serialization code generated by #[derive(Msg)] with #[msg(serde)].
+#[cfg(any(feature = "persist", feature = "record"))]
+#[doc(hidden)]
+pub use relm_state::serde;
+#[cfg(feature = "record")]
+use relm_state::serde::de::DeserializeOwned;
+use relm_state::init_shared_component;
 
 pub use component::Component;
 pub use container::{Container, ContainerComponent, ContainerWidget};
@@ -202,7 +266,7 @@ fn create_widget_test<WIDGET>(executor: &Executor, model_param: WIDGET::ModelPar
           WIDGET::Msg: DisplayVariant + 'static,
 {
     let (widget, component, relm) = create_widget(executor, model_param);
-    init_component::<WIDGET>(widget.stream(), component, executor, &relm);
+    init_widget::<WIDGET>(widget.stream(), component, executor, &relm);
     widget
 }
 
@@ -215,7 +279,7 @@ pub fn create_component<CHILDWIDGET, WIDGET>(relm: &Relm<WIDGET>, model_param: C
           WIDGET: Widget,
 {
     let (widget, component, child_relm) = create_widget::<CHILDWIDGET>(relm.executor(), model_param);
-    init_component::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
+    init_widget::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
     widget
 }
 
@@ -228,15 +292,84 @@ pub fn create_container<CHILDWIDGET, WIDGET>(relm: &Relm<WIDGET>, model_param: C
           WIDGET: Widget,
 {
     let (widget, component, child_relm) = create_widget::<CHILDWIDGET>(relm.executor(), model_param);
-    let container = component.container().clone();
-    let containers = component.other_containers();
-    init_component::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
+    let container = component.borrow().container().clone();
+    let containers = component.borrow().other_containers();
+    init_widget::<CHILDWIDGET>(widget.stream(), component, relm.executor(), &child_relm);
     ContainerComponent::new(widget, container, containers)
 }
 
+/// Initialize a widget by dispatching the messages from the stream and calling its lifecycle
+/// methods when its root widget emits the corresponding signals.
+/// The stream is closed when the root widget is destroyed, once its pending messages are processed.
+fn init_widget<WIDGET>(stream: &EventStream<WIDGET::Msg>, component: Rc<RefCell<WIDGET>>, executor: &Executor,
+    relm: &Relm<WIDGET>)
+    where WIDGET: Widget + 'static,
+          WIDGET::Msg: DisplayVariant + 'static,
+{
+    let root = component.borrow().root();
+    {
+        // NOTE: when the root widget is destroyed during an update, on_destroy() is called when the
+        // stream is closed, once the update is done, so that it is still called before on_close().
+        let destroyed_during_update = Rc::new(Cell::new(false));
+        {
+            let destroyed_during_update = destroyed_during_update.clone();
+            relm.before_close(move |component: &mut WIDGET| {
+                if destroyed_during_update.get() {
+                    component.on_destroy();
+                }
+            });
+        }
+        let component = Rc::downgrade(&component);
+        let stream = stream.downgrade();
+        let _ = root.connect_destroy(move |_| {
+            if let Some(component) = component.upgrade() {
+                match component.try_borrow_mut() {
+                    Ok(mut component) => component.on_destroy(),
+                    Err(_) => destroyed_during_update.set(true),
+                }
+            }
+            if let Some(stream) = stream.upgrade() {
+                // NOTE: the messages emitted right before the window is destroyed (e.g. a Quit
+                // message emitted by the delete_event handler) must still be processed.
+                let _ = stream.close_after_pending();
+            }
+        });
+    }
+    {
+        let component = Rc::downgrade(&component);
+        let executor = executor.clone();
+        let _ = root.connect_map(move |_| call_hook(&component, &executor, WIDGET::on_map));
+    }
+    {
+        let component = Rc::downgrade(&component);
+        let executor = executor.clone();
+        let _ = root.connect_unmap(move |_| call_hook(&component, &executor, WIDGET::on_unmap));
+    }
+    init_shared_component(stream, component, executor, relm);
+}
+
+fn call_hook<WIDGET: 'static>(component: &Weak<RefCell<WIDGET>>, executor: &Executor, hook: fn(&mut WIDGET)) {
+    if let Some(component) = component.upgrade() {
+        match component.try_borrow_mut() {
+            Ok(mut component) => hook(&mut *component),
+            // The signal was emitted during an update: call the hook once it is done.
+            Err(_) => {
+                let component = Rc::downgrade(&component);
+                let hook_executor = executor.clone();
+                let future = future::lazy(move || {
+                    call_hook(&component, &hook_executor, hook);
+                    Ok(())
+                });
+                // NOTE: no error can be returned from execute(), hence unwrap().
+                future::Executor::execute(executor, future).unwrap();
+            },
+        }
+    }
+}
+
 /// Create a new relm widget with `model_param` as initialization value.
 fn create_widget<WIDGET>(executor: &Executor, model_param: WIDGET::ModelParam)
-    -> (Component<WIDGET>, WIDGET, Relm<WIDGET>)
+    -> (Component<WIDGET>, Rc<RefCell<WIDGET>>, Relm<WIDGET>)
     where WIDGET: Widget + 'static,
           WIDGET::Msg: DisplayVariant + 'static,
 {
@@ -248,7 +381,8 @@ fn create_widget<WIDGET>(executor: &Executor, model_param: WIDGET::ModelParam)
     widget.init_view();
 
     let root = widget.root().clone();
-    (Component::new(stream, root), widget, relm)
+    let widget = Rc::new(RefCell::new(widget));
+    (Component::new(stream, root, widget.clone()), widget, relm)
 }
 
 // TODO: remove this workaround.
@@ -326,6 +460,25 @@ pub fn init_test<WIDGET>(model_param: WIDGET::ModelParam) -> Result<Component<WI
     Ok(component)
 }
 
+/// Create a widget for tests and send it the messages recorded for the component `path` in the
+/// file `file` (see [`Recorder`](struct.Recorder.html)).
+///
+/// The messages are queued right away: run the main loop (e.g. with `relm_test::run_loop()`) to
+/// have the component process them.
+#[cfg(feature = "record")]
+pub fn replay_test<WIDGET, P>(model_param: WIDGET::ModelParam, file: P, path: &str)
+    -> io::Result<Component<WIDGET>>
+    where P: AsRef<Path>,
+          WIDGET: Widget + 'static,
+          WIDGET::Msg: DeserializeOwned + DisplayVariant + 'static
+{
+    let replay = Replay::load(file, path)?;
+    let component = init_test::<WIDGET>(model_param)
+        .map_err(|()| io::Error::new(io::ErrorKind::Other, "cannot initialize gtk"))?;
+    replay.replay(component.stream());
+    Ok(component)
+}
+
 /// Initialize a widget.
 pub fn init<WIDGET>(model_param: WIDGET::ModelParam) -> Result<Component<WIDGET>, ()>
     where WIDGET: Widget + 'static,
@@ -336,7 +489,34 @@ pub fn init<WIDGET>(model_param: WIDGET::ModelParam) -> Result<Component<WIDGET>
 
     let executor = create_executor();
     let (widget, component, relm) = create_widget::<WIDGET>(&executor, model_param);
-    init_component::<WIDGET>(widget.stream(), component, &executor, &relm);
+    init_widget::<WIDGET>(widget.stream(), component, &executor, &relm);
+    Ok(widget)
+}
+
+/// Initialize a widget, using `pool_config` to configure the worker pool of
+/// [`Relm::spawn_blocking()`](struct.Relm.html#method.spawn_blocking).
+pub fn init_with_pool<WIDGET>(pool_config: PoolConfig, model_param: WIDGET::ModelParam)
+    -> Result<Component<WIDGET>, ()>
+    where WIDGET: Widget + 'static,
+          WIDGET::Msg: DisplayVariant + 'static
+{
+    init_pool(pool_config);
+    init::<WIDGET>(model_param)
+}
+
+/// Initialize a widget whose futures, including the one dispatching its messages, run from a glib
+/// source of the specified `priority` (e.g. `glib::PRIORITY_DEFAULT_IDLE`).
+/// The components created with its [`Relm`](struct.Relm.html) use the same priority.
+pub fn init_with_priority<WIDGET>(priority: i32, model_param: WIDGET::ModelParam) -> Result<Component<WIDGET>, ()>
+    where WIDGET: Widget + 'static,
+          WIDGET::Msg: DisplayVariant + 'static
+{
+    futures_glib::init();
+    gtk::init().map_err(|_| ())?;
+
+    let executor = create_executor_with_priority(priority);
+    let (widget, component, relm) = create_widget::<WIDGET>(&executor, model_param);
+    init_widget::<WIDGET>(widget.stream(), component, &executor, &relm);
     Ok(widget)
 }
 
@@ -42,6 +42,23 @@ pub trait Widget
     fn on_add<W: IsA<gtk::Widget> + IsA<Object>>(&self, _parent: W) {
     }
 
+    /// Method called when the root widget is destroyed.
+    /// The stream of the component is closed right after, which calls
+    /// [`Update::on_close()`](trait.Update.html#method.on_close).
+    /// When the root widget is destroyed during an update, both methods are called in this order
+    /// once the update returns.
+    fn on_destroy(&mut self) {
+    }
+
+    /// Method called when the root widget is mapped, i.e. when it becomes visible on the screen.
+    fn on_map(&mut self) {
+    }
+
+    /// Method called when the root widget is unmapped, i.e. when it is not visible on the screen
+    /// anymore.
+    fn on_unmap(&mut self) {
+    }
+
     /// Get the parent ID.
     /// This is useful for custom Container implementation: when you implement the
     /// [`Container::add_widget()`](trait.Container.html#tymethod.add_widget), you might want to
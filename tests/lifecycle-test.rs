@@ -0,0 +1,101 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+extern crate gtk;
+#[macro_use]
+extern crate relm;
+#[macro_use]
+extern crate relm_derive;
+extern crate relm_test;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gtk::{Window, WindowType};
+use relm::{Relm, Update, Widget};
+
+use self::Msg::*;
+
+#[derive(Msg)]
+pub enum Msg {
+    Increment,
+}
+
+pub struct Win {
+    count: Rc<Cell<i32>>,
+    window: Window,
+}
+
+impl Update for Win {
+    type Model = Rc<Cell<i32>>;
+    type ModelParam = Rc<Cell<i32>>;
+    type Msg = Msg;
+
+    fn model(_: &Relm<Self>, count: Rc<Cell<i32>>) -> Rc<Cell<i32>> {
+        count
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Increment => self.count.set(self.count.get() + 1),
+        }
+    }
+}
+
+impl Widget for Win {
+    type Root = Window;
+
+    fn root(&self) -> Self::Root {
+        self.window.clone()
+    }
+
+    fn view(_relm: &Relm<Self>, count: Self::Model) -> Self {
+        Win {
+            count,
+            window: Window::new(WindowType::Toplevel),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use gtk::WidgetExt;
+    use relm;
+    use relm_test::run_loop;
+
+    use super::Msg::Increment;
+    use super::Win;
+
+    #[test]
+    fn pending_messages_processed_after_destroy() {
+        let count = Rc::new(Cell::new(0));
+        let component = relm::init_test::<Win>(count.clone()).unwrap();
+        component.emit(Increment);
+        component.widget().destroy();
+        assert!(!component.stream().is_closed());
+        run_loop();
+        assert_eq!(count.get(), 1);
+        assert!(component.stream().is_closed());
+    }
+}
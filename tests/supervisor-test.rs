@@ -0,0 +1,107 @@
+/*
+ * Copyright (c) 2017 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+#![feature(proc_macro)]
+
+extern crate gtk;
+extern crate relm;
+extern crate relm_attributes;
+#[macro_use]
+extern crate relm_derive;
+extern crate relm_test;
+
+use std::cell::Cell;
+
+use gtk::LabelExt;
+use relm::{PanicPolicy, Widget};
+use relm_attributes::widget;
+
+use self::Msg::*;
+
+thread_local! {
+    static COUNTER: Cell<i32> = Cell::new(-1);
+}
+
+pub struct Model {
+    counter: i32,
+}
+
+#[derive(Msg)]
+pub enum Msg {
+    Check,
+    Crash,
+    Increment,
+}
+
+#[widget]
+impl Widget for Win {
+    fn model() -> Model {
+        Model {
+            counter: 0,
+        }
+    }
+
+    fn panic_policy() -> PanicPolicy {
+        PanicPolicy::Restart
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Check => COUNTER.with(|counter| counter.set(self.model.counter)),
+            Crash => {
+                self.model.counter = 100;
+                panic!("crash");
+            },
+            Increment => self.model.counter += 1,
+        }
+    }
+
+    view! {
+        gtk::Window {
+            gtk::Label {
+                text: &self.model.counter.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use relm;
+    use relm_test::run_loop;
+
+    use super::COUNTER;
+    use super::Msg::{Check, Crash, Increment};
+    use super::Win;
+
+    #[test]
+    fn restart_resets_model() {
+        let component = relm::init_test::<Win>(()).unwrap();
+        component.emit(Increment);
+        component.emit(Crash);
+        component.emit(Increment);
+        component.emit(Check);
+        run_loop();
+        // The model modified before the panic was replaced by a fresh one from model().
+        assert_eq!(COUNTER.with(|counter| counter.get()), 1);
+        assert!(!component.stream().is_closed());
+    }
+}